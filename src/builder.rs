@@ -1,5 +1,6 @@
-use core::marker::PhantomData;
-use crate::{generators::row::{Row, FixedSize, FreeSize}, tables::Variant};
+use core::{fmt, marker::PhantomData};
+use crate::{generators::{row::{Row, FixedSize, FreeSize}, bitfield::Bitfield, TruncatedPDF417Row}, tables::Variant};
+use crate::{START_PATTERN, END_PATTERN, MAX_SYMBOL_CODEWORDS, SealError, RegularVariant};
 
 #[cfg(feature = "embedded-graphics")]
 use embedded_graphics::{
@@ -18,11 +19,137 @@ pub const MIN_COLS: u8 = 1;
 /// Maximum number of data columns in a PDF417 barcode.
 pub const MAX_COLS: u8 = 30;
 
+/// Returns the number of pixels needed to print `modules` modules with an
+/// X-dimension (module width) of `x_dimension_mils` thousandths of an inch on
+/// a printer of `dpi` dots per inch. The module width is rounded to the
+/// nearest whole number of pixels (at least 1) so that all the modules have
+/// the same width, the printed X-dimension can thus slightly differ from the
+/// requested one.
+pub fn modules_to_pixels(modules: u32, x_dimension_mils: f32, dpi: f32) -> u32 {
+    modules * module_pixels(x_dimension_mils, dpi)
+}
+
+/// Width in pixels of a module (rounded to the nearest pixel, at least 1).
+fn module_pixels(x_dimension_mils: f32, dpi: f32) -> u32 {
+    let pixels = x_dimension_mils * dpi / 1000.0;
+    // no f32::round in core, the value is positive
+    ((pixels + 0.5) as u32).max(1)
+}
+
+/// Error returned when a PDF417 configuration is invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The number of rows is not between [MIN_ROWS] and [MAX_ROWS].
+    InvalidRows(u8),
+    /// The number of columns is not between [MIN_COLS] and [MAX_COLS].
+    InvalidCols(u8),
+    /// The ECC level is not between 0 and 8.
+    InvalidLevel(u8),
+    /// The storage length is not equal to rows \* cols.
+    StorageSize { rows: u8, cols: u8, actual: usize },
+    /// The length codeword (first codeword) is not equal to the number of
+    /// data codewords rows \* cols - ECC codewords.
+    LengthMismatch { expected: usize, actual: u16 },
+    /// The rendered rows `[start, end)` are empty or not within the rows.
+    RowRange { start: u8, end: u8, rows: u8 },
+    /// The symbol has more than [MAX_SYMBOL_CODEWORDS] codewords
+    /// (rows \* cols).
+    TooLarge { rows: u8, cols: u8 },
+    /// The ECC codewords and the length codeword do not fit in the symbol.
+    EccOverflow { ecc: usize, capacity: usize },
+    /// The scale is 0 on at least one axis.
+    InvalidScale(u16, u16),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ConfigError::InvalidRows(rows) =>
+                write!(f, "invalid number of rows {rows} (must be between {MIN_ROWS} and {MAX_ROWS})"),
+            ConfigError::InvalidCols(cols) =>
+                write!(f, "invalid number of columns {cols} (must be between {MIN_COLS} and {MAX_COLS})"),
+            ConfigError::InvalidLevel(level) =>
+                write!(f, "invalid ECC level {level} (must be between 0 and 8)"),
+            ConfigError::StorageSize { rows, cols, actual } =>
+                write!(f, "expected {} codewords ({rows}x{cols}), got {actual}", rows as usize * cols as usize),
+            ConfigError::LengthMismatch { expected, actual } =>
+                write!(f, "the length codeword must be {expected}, got {actual}"),
+            ConfigError::RowRange { start, end, rows } =>
+                write!(f, "invalid row range [{start}, {end}) for {rows} rows"),
+            ConfigError::TooLarge { rows, cols } =>
+                write!(f, "{rows}x{cols} codewords exceed the maximum of {MAX_SYMBOL_CODEWORDS} codewords"),
+            ConfigError::EccOverflow { ecc, capacity } =>
+                write!(f, "{ecc} ECC codewords do not fit in a symbol of {capacity} codewords"),
+            ConfigError::InvalidScale(sx, sy) =>
+                write!(f, "invalid scale ({sx}, {sy}) (must be at least 1 on both axes)"),
+        }
+    }
+}
+
+/// Error returned by the checked rendering methods when the target buffer
+/// does not have the exact size of the rendering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeError {
+    /// Required length of the target.
+    pub expected: usize,
+    /// Length of the target.
+    pub actual: usize,
+}
+
+impl fmt::Display for SizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the target must hold exactly {} elements, got {}", self.expected, self.actual)
+    }
+}
+
+/// Any error returned by the `try_` methods of the encoding and rendering
+/// pipeline, so that a whole pipeline can be written with `?` without
+/// panicking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdfError {
+    /// See [PDF417Encoder::try_seal](crate::PDF417Encoder::try_seal).
+    Seal(SealError),
+    /// See [PDF417::try_new].
+    Config(ConfigError),
+    /// See [PDF417Render::try_fill].
+    Size(SizeError),
+}
+
+impl fmt::Display for PdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PdfError::Seal(e) => e.fmt(f),
+            PdfError::Config(e) => e.fmt(f),
+            PdfError::Size(e) => e.fmt(f),
+        }
+    }
+}
+
+impl From<SealError> for PdfError {
+    fn from(e: SealError) -> Self {
+        PdfError::Seal(e)
+    }
+}
+
+impl From<ConfigError> for PdfError {
+    fn from(e: ConfigError) -> Self {
+        PdfError::Config(e)
+    }
+}
+
+impl From<SizeError> for PdfError {
+    fn from(e: SizeError) -> Self {
+        PdfError::Size(e)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PDF417<'a, R: Row<'a> + 'a> {
     storage: &'a [u16],
     dimensions: (u8, u8),
     level: u8,
+    /// Custom (start, end) patterns
+    patterns: Option<(Bitfield, Bitfield)>,
     _phantom: PhantomData<R>,
 }
 
@@ -47,10 +174,36 @@ impl<'a, R: Row<'a> + 'a> PDF417<'a, R> {
 
     pub fn iter(&self) -> impl Iterator<Item = R> + 'a {
         let infos = R::prepare(self.dimensions, self.level);
+        let patterns = self.patterns;
         // TODO: should it be chunks_exact ?
         self.storage.chunks(self.cols() as usize)
             .enumerate()
-            .map(move |(row, codewords)| R::init(codewords, row as u8, infos))
+            .map(move |(row, codewords)| {
+                let mut row = R::init(codewords, row as u8, infos);
+                if let Some((start, end)) = patterns {
+                    row.set_patterns(start, end);
+                }
+                row
+            })
+    }
+
+    /// Returns an iterator over the codewords with their position in the
+    /// symbol grid as (row, column, codeword). The column is relative to the
+    /// data region (excluding the row indicators).
+    pub fn codewords_positioned(&self) -> impl Iterator<Item = (u8, u8, u16)> + 'a {
+        let cols = self.cols() as usize;
+        self.storage.iter()
+            .enumerate()
+            .map(move |(i, &cw)| ((i / cols) as u8, (i % cols) as u8, cw))
+    }
+
+    /// Returns true if all the data codewords (excluding the length codeword
+    /// of a regular PDF417) are padding ([CW_PADDING](crate::CW_PADDING)),
+    /// i.e. the symbol is valid but holds no data.
+    pub fn is_effectively_empty(&self) -> bool {
+        let data = self.storage.len().saturating_sub(R::ecc_count(self.level));
+        self.storage.get(R::DATA_OFFSET..data)
+            .map_or(true, |data| data.iter().all(|&cw| cw == crate::CW_PADDING))
     }
 
     pub fn bits(&self) -> impl Iterator<Item = bool> + 'a {
@@ -59,25 +212,150 @@ impl<'a, R: Row<'a> + 'a> PDF417<'a, R> {
             .flatten() // bitfield -> bits
     }
 
+    /// Returns the width in modules of a row of the PDF417 (without the
+    /// scale and the quiet zone).
+    pub fn width(&self) -> u32 {
+        R::width(self.dimensions)
+    }
+
+    /// Returns the height of the PDF417 without the scale and the quiet
+    /// zone, i.e. its number of rows.
+    pub const fn height(&self) -> u32 {
+        self.rows() as u32
+    }
+
+    /// Returns an iterator over the rows of the PDF417 as arrays of `W`
+    /// modules (from the start pattern to the end pattern), e.g. to feed a
+    /// shift register. Panics if `W` is not the [width](PDF417::width) of
+    /// the PDF417.
+    pub fn rows_as_bits<const W: usize>(&self) -> impl Iterator<Item = [bool; W]> + 'a {
+        assert!(W == self.width() as usize, "W must be equal to the width of the PDF417 in modules");
+        self.iter().map(|row| {
+            let mut bits = [false; W];
+            for (bit, module) in bits.iter_mut().zip(row.flatten()) {
+                *bit = module;
+            }
+            bits
+        })
+    }
+
+    /// Returns the number of modules yielded by [bits](PDF417::bits) (row
+    /// width in modules \* rows) without iterating over the rows.
+    pub fn total_bits(&self) -> usize {
+        self.width() as usize * self.height() as usize
+    }
+
     pub const fn render(self) -> PDF417Render<'a, R> {
+         let rows = self.rows();
          PDF417Render {
              inner: self,
              scale: R::DEFAULT_SCALE,
              inverted: false,
+             quiet_zone: 0,
+             row_range: (0, rows),
+             text_band: 0,
+             calibration: CalibrationSpec { solid_rows: 0, blank_rows: 0, striped_rows: 0 },
 
              #[cfg(feature = "embedded-graphics")]
              top_left: Point::zero()
          }
     }
+
+    /// Renders the PDF417 with the settings of `config`, the settings can
+    /// still be changed afterwards using the setters of [PDF417Render].
+    pub const fn render_with(self, config: RenderConfig) -> PDF417Render<'a, R> {
+        let mut render = self.render();
+        if let Some(scale) = config.scale {
+            render.scale = scale;
+        }
+        render.inverted = config.inverted;
+        render.quiet_zone = config.quiet_zone;
+        render
+    }
+}
+
+/// Reusable rendering settings applied by [PDF417::render_with].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RenderConfig {
+    /// None to use the default scale of the symbology.
+    scale: Option<(u16, u16)>,
+    inverted: bool,
+    quiet_zone: u16,
+}
+
+impl RenderConfig {
+    /// Creates a configuration with the default settings of
+    /// [PDF417::render]: default scale of the symbology, not inverted and no
+    /// quiet zone.
+    pub const fn new() -> Self {
+        Self { scale: None, inverted: false, quiet_zone: 0 }
+    }
+
+    /// See [PDF417Render::set_scale].
+    pub const fn set_scale(mut self, scale: (u16, u16)) -> Self {
+        debug_assert!(scale.0 > 0 && scale.1 > 0, "The scale must be at least 1 on both axes");
+        self.scale = Some(scale);
+        self
+    }
+
+    /// See [PDF417Render::set_inverted].
+    pub const fn set_inverted(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+
+    /// See [PDF417Render::set_quiet_zone].
+    pub const fn set_quiet_zone(mut self, modules: u16) -> Self {
+        self.quiet_zone = modules;
+        self
+    }
+}
+
+/// Calibration bars rendered below a symbol to verify the print quality,
+/// see [PDF417Render::with_calibration_bars]. The number of rows are
+/// expressed in modules (each row being `scale.1` pixels high).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CalibrationSpec {
+    /// Number of rows with all the modules on.
+    pub solid_rows: u16,
+    /// Number of rows with all the modules off.
+    pub blank_rows: u16,
+    /// Number of rows with alternating on and off modules (starting on).
+    pub striped_rows: u16,
+}
+
+impl CalibrationSpec {
+    /// Total number of rows of the calibration bars.
+    pub const fn rows(&self) -> u32 {
+        self.solid_rows as u32 + self.blank_rows as u32 + self.striped_rows as u32
+    }
+}
+
+/// Content of a line of pixels, see [PDF417Render::pixels].
+#[derive(Clone)]
+enum Line<R> {
+    /// Quiet zone
+    Quiet,
+    /// Row of the symbol
+    Symbol(R),
+    /// Row of modules all set to the value
+    Solid(bool),
+    /// Row of alternating modules, starting on
+    Striped,
 }
 
 impl<'a, R: Row<'a> + 'a + FixedSize> PDF417<'a, R> {
+    /// Creates a MicroPDF417 of the variant `v` from codewords sealed by a
+    /// [PDF417Encoder](crate::PDF417Encoder) created with `micro` set to
+    /// true. Panics if the length of the storage is not the number of
+    /// codewords of the variant (rows \* cols), which usually means the
+    /// codewords were encoded for a regular PDF417 or another variant.
     pub const fn from_variant(storage: &'a [u16], v: Variant) -> Self {
         let dimensions = (v.rows(), v.cols());
         assert!(storage.len() == (dimensions.0 as usize * dimensions.1 as usize),
-            "The data will not fit in the provided configuration");
+            "The storage length must be equal to the number of codewords of the MicroPDF417 variant (rows * cols)");
 
-        Self { storage, dimensions, level: v.variant(), _phantom: PhantomData }
+        Self { storage, dimensions, level: v.variant(), patterns: None, _phantom: PhantomData }
     }
 }
 
@@ -88,16 +366,116 @@ impl<'a, R: Row<'a> + 'a + FreeSize> PDF417<'a, R> {
     /// rows \* cols and must be greater or equal to the number of codewords
     /// in the `codewords` slice. Please make sure your codewords
     /// slice is valid, you can use [PDF417Encoder] to fill it accordingly.
+    /// Panics if the configuration is rejected by [PDF417::try_new].
     pub const fn new(storage: &'a [u16], rows: u8, cols: u8, level: u8) -> Self {
-        assert!(rows >= MIN_ROWS && rows <= MAX_ROWS, "The number of rows must be between 3 and 90");
-        assert!(cols >= MIN_COLS && cols <= MAX_COLS, "The number of columns must be between 1 and 30");
-        assert!(storage.len() == (rows as usize * cols as usize),
-            "The data will not fit in the provided configuration");
-        assert!(level < 9, "ECC level must be between 0 and 8");
+        match Self::try_new(storage, rows, cols, level) {
+            Ok(pdf417) => pdf417,
+            Err(ConfigError::InvalidRows(_)) => panic!("The number of rows must be between 3 and 90"),
+            Err(ConfigError::InvalidCols(_)) => panic!("The number of columns must be between 1 and 30"),
+            Err(ConfigError::InvalidLevel(_)) => panic!("ECC level must be between 0 and 8"),
+            Err(ConfigError::StorageSize { .. }) =>
+                panic!("The storage length must be equal to rows * cols (see PDF417::try_new for details)"),
+            Err(ConfigError::TooLarge { .. }) =>
+                panic!("The symbol must have at most 928 codewords (see PDF417::try_new for details)"),
+            Err(_) => panic!("The ECC codewords must fit in the symbol (see PDF417::try_new for details)"),
+        }
+    }
+
+    /// Same as [PDF417::new] but returns a [ConfigError] describing the
+    /// invalid parameter instead of panicking. The configuration is checked
+    /// by [RegularVariant::try_new], so symbols with more than
+    /// [MAX_SYMBOL_CODEWORDS] codewords or whose ECC codewords do not fit
+    /// are rejected as well.
+    pub const fn try_new(storage: &'a [u16], rows: u8, cols: u8, level: u8) -> Result<Self, ConfigError> {
+        let config = match RegularVariant::try_new(rows, cols, level) {
+            Ok(config) => config,
+            Err(e) => return Err(e),
+        };
+        if storage.len() != rows as usize * cols as usize {
+            return Err(ConfigError::StorageSize { rows, cols, actual: storage.len() });
+        }
+
+        Ok(Self::from_config(storage, config))
+    }
+
+    /// Creates a PDF417 from a configuration validated once by
+    /// [RegularVariant::try_new], e.g. to render many symbols with the same
+    /// layout. Only the length of the storage is checked, panics if it is
+    /// not rows \* cols.
+    pub const fn from_config(storage: &'a [u16], config: RegularVariant) -> Self {
+        let dimensions = (config.rows(), config.cols());
+        assert!(storage.len() == (dimensions.0 as usize * dimensions.1 as usize),
+            "The storage length must be equal to rows * cols (see PDF417::try_new for details)");
+
+        Self { storage, dimensions, level: config.level(), patterns: None, _phantom: PhantomData }
+    }
+
+    /// Checks that the length codeword (first codeword) is equal to the
+    /// number of data codewords (rows \* cols - ECC codewords) as written by
+    /// [PDF417Encoder::seal](crate::PDF417Encoder::seal), e.g. before
+    /// rendering codewords provided by a third party. The ECC codewords are
+    /// not checked. Returns [ConfigError::EccOverflow] if the ECC codewords
+    /// and the length codeword do not fit in the symbol.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let (ecc, capacity) = (R::ecc_count(self.level), self.storage.len());
+        let expected = match capacity.checked_sub(ecc) {
+            Some(expected) if expected > 0 => expected,
+            _ => return Err(ConfigError::EccOverflow { ecc, capacity }),
+        };
+        match self.storage[0] {
+            length if length as usize == expected => Ok(()),
+            actual => Err(ConfigError::LengthMismatch { expected, actual }),
+        }
+    }
+
+    /// Overrides the start and end patterns (defaults to [START_PATTERN] and
+    /// [END_PATTERN]) for readers expecting a private symbology variant. The
+    /// patterns must have the same size as the standard ones. Truncated
+    /// PDF417s only use the start pattern.
+    pub const fn set_patterns(mut self, start: Bitfield, end: Bitfield) -> Self {
+        assert!(start.size() == START_PATTERN.size(), "The start pattern must be 17 modules wide");
+        assert!(end.size() == END_PATTERN.size(), "The end pattern must be 18 modules wide");
+        self.patterns = Some((start, end));
+        self
+    }
+}
+
+/// Number of rows above which a truncated PDF417 is flagged by
+/// [PDF417::is_unreliable]. The specification only recommends truncation for
+/// clean environments without giving a limit, this conservative threshold
+/// is advisory.
+pub const MAX_RELIABLE_TRUNCATED_ROWS: u8 = 30;
 
-        Self { storage, dimensions: (rows, cols), level, _phantom: PhantomData }
+impl<'a> PDF417<'a, TruncatedPDF417Row<'a>> {
+    /// Returns true if the truncated PDF417 has more than
+    /// [MAX_RELIABLE_TRUNCATED_ROWS] rows. Without the right row indicators
+    /// and the end pattern, a scanner has less redundancy to locate the rows
+    /// of a tall symbol, consider a regular PDF417 in that case.
+    pub const fn is_unreliable(&self) -> bool {
+        self.rows() > MAX_RELIABLE_TRUNCATED_ROWS
     }
+}
 
+/// Layout information of a rendered PDF417, see [PDF417Render::info].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SymbolInfo {
+    /// Width in pixels (scale included).
+    pub width: u32,
+    /// Height in pixels (scale included).
+    pub height: u32,
+    /// Number of rows.
+    pub rows: u8,
+    /// Number of data columns.
+    pub cols: u8,
+    /// Total number of modules (unscaled width \* rows).
+    pub modules: u32,
+    /// Number of data codewords (including the length and padding codewords),
+    /// 0 if the ECC codewords do not fit in the symbol.
+    pub data_codewords: usize,
+    /// Number of ECC codewords.
+    pub ecc_codewords: usize,
+    /// Width divided by height.
+    pub aspect_ratio: f32,
 }
 
 #[derive(Debug, Clone)]
@@ -105,6 +483,13 @@ pub struct PDF417Render<'a, R: Row<'a> + 'a> {
     inner: PDF417<'a, R>,
     scale: (u16, u16),
     inverted: bool,
+    quiet_zone: u16,
+    /// Rendered rows [start, end)
+    row_range: (u8, u8),
+    /// Blank module rows below the symbol
+    text_band: u16,
+    /// Test rows below the text band
+    calibration: CalibrationSpec,
 
     #[cfg(feature = "embedded-graphics")]
     top_left: geometry::Point,
@@ -117,17 +502,55 @@ impl<'a, R: Row<'a> + 'a> From<PDF417<'a, R>> for PDF417Render<'a, R> {
 }
 
 impl<'a, R: Row<'a> + 'a> PDF417Render<'a, R> {
+    /// Returns the width in pixels of the rendered PDF417 including the quiet
+    /// zone.
     pub fn width(&self) -> u32 {
-        R::width(self.inner.dimensions) * self.scale.0 as u32
+        R::width(self.inner.dimensions) * self.scale.0 as u32 + 2 * self.margin()
     }
 
+    /// Returns the height in pixels of the rendered PDF417 including the
+    /// quiet zone.
     pub const fn height(&self) -> u32 {
-        self.inner.rows() as u32 * self.scale.1 as u32
+        let (start, end) = self.row_range;
+        ((end - start) as u32 + self.extra_rows()) * self.scale.1 as u32 + 2 * self.margin()
     }
 
     #[cfg(feature = "embedded-graphics")]
     pub fn size(&self) -> geometry::Size {
-        geometry::Size::new(self.width() as u32, self.height() as u32)
+        geometry::Size::new(self.width(), self.height())
+    }
+
+    /// Returns the number of pixels yielded by [bits](PDF417Render::bits),
+    /// i.e. `width * height` with the scale, the quiet zone and the text band
+    /// included.
+    pub fn total_bits(&self) -> usize {
+        self.width() as usize * self.height() as usize
+    }
+
+    /// Returns the layout information (dimensions, codewords) of the PDF417.
+    pub fn info(&self) -> SymbolInfo {
+        let (width, height) = (self.width(), self.height());
+        let capacity = self.inner.rows() as usize * self.inner.cols() as usize;
+        let ecc_codewords = R::ecc_count(self.inner.level);
+        SymbolInfo {
+            width,
+            height,
+            rows: self.inner.rows(),
+            cols: self.inner.cols(),
+            modules: R::width(self.inner.dimensions) * self.inner.rows() as u32,
+            data_codewords: capacity.saturating_sub(ecc_codewords),
+            ecc_codewords,
+            aspect_ratio: width as f32 / height as f32,
+        }
+    }
+
+    /// Returns the error-correction capability of the PDF417 as (maximum
+    /// number of errors, maximum number of erasures) it can recover from,
+    /// derived from its number of ECC codewords (errors count twice as they
+    /// also need to be located).
+    pub fn correction_capacity(&self) -> (usize, usize) {
+        let ecc = R::ecc_count(self.inner.level);
+        (ecc / 2, ecc)
     }
 
     /// Returns the scale of the PDF417 as (Scale X axis, Scale Y axis).
@@ -137,10 +560,22 @@ impl<'a, R: Row<'a> + 'a> PDF417Render<'a, R> {
 
     /// Sets the scale of the PDF417 on both axis. See also [scaled](PDF417::scaled).
     pub const fn set_scale(mut self, scale: (u16, u16)) -> Self {
+        debug_assert!(scale.0 > 0 && scale.1 > 0, "The scale must be at least 1 on both axes");
         self.scale = scale;
         self
     }
 
+    /// Same as [set_scale](PDF417Render::set_scale) but returns a
+    /// [ConfigError] instead of setting a scale of 0 on any axis, which
+    /// would render an empty symbol.
+    pub const fn try_set_scale(mut self, scale: (u16, u16)) -> Result<Self, ConfigError> {
+        if scale.0 == 0 || scale.1 == 0 {
+            return Err(ConfigError::InvalidScale(scale.0, scale.1));
+        }
+        self.scale = scale;
+        Ok(self)
+    }
+
     /// Returns if the PDF417 is set to be rendered with inverted colors.
     pub const fn inverted(&self) -> bool {
         self.inverted
@@ -152,6 +587,142 @@ impl<'a, R: Row<'a> + 'a> PDF417Render<'a, R> {
         self
     }
 
+    /// Returns the scale to use to print the PDF417 with an X-dimension of
+    /// `x_dimension_mils` thousandths of an inch on a printer of `dpi` dots
+    /// per inch (see [modules_to_pixels] for the rounding). The row height
+    /// keeps its ratio to the module width of the current scale (e.g. rows 3
+    /// modules high with a scale of (1, 3)), rounded to the nearest pixel.
+    pub fn print_scale(&self, x_dimension_mils: f32, dpi: f32) -> (u16, u16) {
+        let (sx, sy) = self.scale;
+        let module = module_pixels(x_dimension_mils, dpi);
+        let row = ((module * sy as u32 + sx as u32 / 2) / sx as u32).max(1);
+        (module as u16, row as u16)
+    }
+
+    /// Returns the size in pixels (width, height) of the PDF417 printed with
+    /// the scale given by [print_scale](PDF417Render::print_scale).
+    pub fn print_size(&self, x_dimension_mils: f32, dpi: f32) -> (u32, u32) {
+        let (sx, sy) = self.print_scale(x_dimension_mils, dpi);
+        let (start, end) = self.row_range;
+        let margin = 2 * self.quiet_zone as u32 * sx as u32;
+        let rows = (end - start) as u32 + self.extra_rows();
+        (R::width(self.inner.dimensions) * sx as u32 + margin, rows * sy as u32 + margin)
+    }
+
+    /// Returns the width of the quiet zone in modules.
+    pub const fn quiet_zone(&self) -> u16 {
+        self.quiet_zone
+    }
+
+    /// Surrounds the PDF417 with a quiet zone of `modules` modules (0 by
+    /// default) on all sides. The quiet zone is part of the rendered pixels,
+    /// its size in pixels is `modules * scale.0` as a module is `scale.0`
+    /// pixels wide. Quiet zone pixels are rendered as "off" modules, they are
+    /// thus set when the rendering is [inverted](PDF417Render::set_inverted).
+    pub const fn set_quiet_zone(mut self, modules: u16) -> Self {
+        self.quiet_zone = modules;
+        self
+    }
+
+    /// Returns the range of rows `[start, end)` being rendered.
+    pub const fn row_range(&self) -> (u8, u8) {
+        self.row_range
+    }
+
+    /// Restricts the rendering (and the [height](PDF417Render::height)) to
+    /// the rows `[start, end)` of the PDF417, e.g. to show a tall symbol
+    /// through a small window. Each row carries its own start and stop
+    /// patterns so the rendered rows are identical to the ones of the full
+    /// symbol. The quiet zone surrounds the selected rows.
+    pub const fn set_row_range(self, start: u8, end: u8) -> Self {
+        match self.try_set_row_range(start, end) {
+            Ok(render) => render,
+            Err(_) => panic!("The row range must be within the rows of the PDF417"),
+        }
+    }
+
+    /// Same as [set_row_range](PDF417Render::set_row_range) but returns a
+    /// [ConfigError] instead of panicking if the range is invalid.
+    pub const fn try_set_row_range(mut self, start: u8, end: u8) -> Result<Self, ConfigError> {
+        let rows = self.inner.rows();
+        if start >= end || end > rows {
+            return Err(ConfigError::RowRange { start, end, rows });
+        }
+        self.row_range = (start, end);
+        Ok(self)
+    }
+
+    /// Returns the number of blank module rows reserved below the symbol.
+    pub const fn text_band(&self) -> u16 {
+        self.text_band
+    }
+
+    /// Reserves `module_rows` blank rows (0 by default) below the symbol,
+    /// inside the quiet zone, e.g. to draw the encoded data as human readable
+    /// text. PDF417 has no standard human readable interpretation, the band
+    /// is only rendered as "off" pixels. Each row is `scale.1` pixels high,
+    /// see [text_band_origin](PDF417Render::text_band_origin) to locate it.
+    pub const fn set_text_band(mut self, module_rows: u16) -> Self {
+        self.text_band = module_rows;
+        self
+    }
+
+    /// Returns the position in pixels (x, y) of the top-left corner of the
+    /// text band (see [set_text_band](PDF417Render::set_text_band)) relative
+    /// to the top-left corner of the rendering. The band is as wide as the
+    /// symbol (quiet zone excluded).
+    pub const fn text_band_origin(&self) -> (u32, u32) {
+        let (start, end) = self.row_range;
+        (self.margin(), self.margin() + (end - start) as u32 * self.scale.1 as u32)
+    }
+
+    /// Returns the rectangle in pixels `(x, y, w, h)` of the data columns of
+    /// the rendered rows, excluding the start and stop patterns and the row
+    /// indicators, relative to the top-left corner of the rendering. For
+    /// MicroPDF417s of 3 or 4 columns, the region includes the center row
+    /// address pattern which splits the data columns.
+    pub fn data_region(&self) -> (u32, u32, u32, u32) {
+        let (start, end) = self.row_range;
+        let (offset, width) = R::data_region(self.inner.dimensions);
+        let (sx, sy) = (self.scale.0 as u32, self.scale.1 as u32);
+        (self.margin() + offset * sx, self.margin(), width * sx, (end - start) as u32 * sy)
+    }
+
+    /// Appends the calibration bars `bars` below the symbol (and the text
+    /// band), as wide as the symbol, e.g. to check the print quality with a
+    /// scanner verifier. The bars are ordered as solid, blank then striped
+    /// rows, they increase the [height](PDF417Render::height) by
+    /// `bars.rows() * scale.1` pixels.
+    pub const fn with_calibration_bars(mut self, bars: CalibrationSpec) -> Self {
+        self.calibration = bars;
+        self
+    }
+
+    /// Number of module rows below the symbol (text band and calibration
+    /// bars).
+    const fn extra_rows(&self) -> u32 {
+        self.text_band as u32 + self.calibration.rows()
+    }
+
+    /// Returns the indices of the rows whose modules differ between this
+    /// PDF417 and `other` (e.g. two symbols of the same dimensions holding
+    /// different data), so that only these rows are re-rendered using
+    /// [set_row_range](PDF417Render::set_row_range). The rows are compared
+    /// lazily, pattern by pattern. Both PDF417s must have the same
+    /// dimensions.
+    pub fn diff_rows(&self, other: &PDF417Render<'a, R>) -> impl Iterator<Item = u8> + 'a {
+        assert_eq!(self.inner.dimensions, other.inner.dimensions, "The PDF417s must have the same dimensions");
+        self.inner.iter().zip(other.inner.iter())
+            .enumerate()
+            .filter(|(_, (a, b))| a.clone().map(|p| p.as_pair()).ne(b.clone().map(|p| p.as_pair())))
+            .map(|(i, _)| i as u8)
+    }
+
+    /// Size in pixels of the quiet zone.
+    const fn margin(&self) -> u32 {
+        self.quiet_zone as u32 * self.scale.0 as u32
+    }
+
     #[cfg(feature = "embedded-graphics")]
     pub const fn top_left(&self) -> geometry::Point {
         self.top_left
@@ -163,14 +734,74 @@ impl<'a, R: Row<'a> + 'a> PDF417Render<'a, R> {
         self
     }
 
-    pub fn bits(&self) -> impl Iterator<Item = bool> + 'a {
+    /// Returns an iterator over the rendered lines of pixels (top to bottom),
+    /// each line yielding [width](PDF417Render::width) pixels.
+    pub fn lines(&self) -> impl Iterator<Item = impl Iterator<Item = bool> + 'a> + 'a {
+        let invert = self.inverted;
+        self.pixels().map(move |line| line.map(move |pixel| pixel.unwrap_or(invert)))
+    }
+
+    /// Lines of pixels where the quiet zone pixels are None.
+    fn pixels(&self) -> impl Iterator<Item = impl Iterator<Item = Option<bool>> + 'a> + 'a {
+        use core::iter::repeat;
         let (sx, sy) = self.scale;
         let invert = self.inverted;
-        self.inner.iter()
-            .flat_map(move |row| core::iter::repeat(row).take(sy as usize))
-            .flatten() // rows -> bitfields
-            .flatten() // bitfield -> bits
-            .flat_map(move |bit| core::iter::repeat(bit ^ invert).take(sx as usize))
+        let margin = self.margin() as usize;
+        let width = R::width(self.inner.dimensions) as usize * sx as usize;
+        let (start, end) = self.row_range;
+        let bars = self.calibration;
+        let rows = self.inner.iter()
+            .skip(start as usize)
+            .take((end - start) as usize)
+            .map(Line::Symbol)
+            .chain(repeat(Line::Solid(false)).take(self.text_band as usize)) // text band
+            .chain(repeat(Line::Solid(true)).take(bars.solid_rows as usize))
+            .chain(repeat(Line::Solid(false)).take(bars.blank_rows as usize))
+            .chain(repeat(Line::Striped).take(bars.striped_rows as usize))
+            .flat_map(move |line| repeat(line).take(sy as usize));
+        repeat(Line::Quiet).take(margin).chain(rows).chain(repeat(Line::Quiet).take(margin))
+            .map(move |line| {
+                // (quiet pixels, symbol row, filled modules as (on, striped))
+                let (quiet, row, filled) = match line {
+                    Line::Quiet => (width, None, None),
+                    Line::Symbol(row) => (0, Some(row), None),
+                    Line::Solid(on) => (0, None, Some((on, false))),
+                    Line::Striped => (0, None, Some((true, true))),
+                };
+                let fill = filled.into_iter()
+                    .flat_map(move |(on, striped)| (0..width)
+                        .map(move |x| Some((on && !(striped && (x / sx as usize) % 2 == 1)) ^ invert)));
+                let bits = row.into_iter()
+                    .flatten() // row -> bitfields
+                    .flatten() // bitfield -> bits
+                    .flat_map(move |bit| repeat(Some(bit ^ invert)).take(sx as usize));
+                repeat(None).take(margin + quiet)
+                    .chain(fill)
+                    .chain(bits)
+                    .chain(repeat(None).take(margin))
+            })
+    }
+
+    pub fn bits(&self) -> impl Iterator<Item = bool> + 'a {
+        self.lines().flatten()
+    }
+
+    /// Returns an iterator over the rendered lines encoded as runs of
+    /// identical pixels `(value, length)`, e.g. to transmit the symbol over
+    /// a slow link. The lengths of the runs of a line add up to
+    /// [width](PDF417Render::width).
+    pub fn rle_rows(&self) -> impl Iterator<Item = impl Iterator<Item = (bool, u32)> + 'a> + 'a {
+        self.lines().map(|line| {
+            let mut line = line.peekable();
+            core::iter::from_fn(move || {
+                let value = line.next()?;
+                let mut length = 1;
+                while line.next_if_eq(&value).is_some() {
+                    length += 1;
+                }
+                Some((value, length))
+            })
+        })
     }
 
     pub fn fill<P: Clone>(&self, target: &mut [P], on: &P, off: &P) {
@@ -179,26 +810,209 @@ impl<'a, R: Row<'a> + 'a> PDF417Render<'a, R> {
         }
     }
 
+    /// Same as [fill](PDF417Render::fill) but returns a [SizeError] if the
+    /// target does not hold exactly `width * height` pixels.
+    pub fn try_fill<P: Clone>(&self, target: &mut [P], on: &P, off: &P) -> Result<(), SizeError> {
+        let expected = self.width() as usize * self.height() as usize;
+        if target.len() != expected {
+            return Err(SizeError { expected, actual: target.len() });
+        }
+        self.fill(target, on, off);
+        Ok(())
+    }
+
+    /// Same as [fill](PDF417Render::fill) but the pixels of the quiet zone
+    /// (see [set_quiet_zone](PDF417Render::set_quiet_zone)) are set to
+    /// `quiet` instead of `off`, e.g. to match a tinted label background.
+    pub fn fill_with_quiet_zone<P: Clone>(&self, target: &mut [P], on: &P, off: &P, quiet: &P) {
+        for (i, pixel) in self.pixels().flatten().enumerate() {
+            target[i] = match pixel {
+                Some(true) => on.clone(),
+                Some(false) => off.clone(),
+                None => quiet.clone(),
+            };
+        }
+    }
+
+    /// Same as [fill](PDF417Render::fill) but the pixels inside the
+    /// `knockout` rectangle (x, y, width, height in pixels of the rendering)
+    /// are set to `fill`, e.g. to overlay a logo. The occluded modules must
+    /// be recoverable by the ECC codewords, keep the rectangle small and the
+    /// ECC level high enough.
+    pub fn fill_with_knockout<P: Clone>(&self, target: &mut [P], on: &P, off: &P, knockout: (u32, u32, u32, u32), fill: &P) {
+        let (kx, ky, kw, kh) = knockout;
+        let width = self.width();
+        for (i, bit) in self.bits().enumerate() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            target[i] = if (kx..kx + kw).contains(&x) && (ky..ky + kh).contains(&y) {
+                fill.clone()
+            } else if bit {
+                on.clone()
+            } else {
+                off.clone()
+            };
+        }
+    }
+
     pub fn fill_bits(&self, target: &mut [bool]) {
         self.fill(target, &true, &false);
     }
 
-    pub fn fill_bitmap(&self, target: &mut [u8]) {
-        let mut row_start = 0;
-        let mut col = 0;
+    /// Same as [fill_bits](PDF417Render::fill_bits) but returns a
+    /// [SizeError] if the target does not hold exactly `width * height`
+    /// pixels.
+    pub fn try_fill_bits(&self, target: &mut [bool]) -> Result<(), SizeError> {
+        self.try_fill(target, &true, &false)
+    }
+
+    /// Same as [fill_bits](PDF417Render::fill_bits) but the even lines of
+    /// pixels (0, 2, 4...) are written to `even` and the odd lines to `odd`,
+    /// e.g. for displays scanning the lines in two fields. Each buffer holds
+    /// `width * ceil(height / 2)` pixels.
+    pub fn fill_bits_interlaced(&self, even: &mut [bool], odd: &mut [bool]) {
         let width = self.width() as usize;
-        for bit in self.bits() {
-            target[row_start + col / 8] |= (bit as u8) << (7 - col % 8);
+        let mut even = even.chunks_mut(width);
+        let mut odd = odd.chunks_mut(width);
+        for (i, line) in self.lines().enumerate() {
+            let target = if i % 2 == 0 { even.next() } else { odd.next() };
+            for (bit, pixel) in line.zip(target.expect("The target is not high enough").iter_mut()) {
+                *pixel = bit;
+            }
+        }
+    }
 
-            col += 1;
-            if col == width {
-                row_start += 1 + col / 8;
-                col = 0;
+    /// Renders the PDF417 into the top-left corner of a 2D array indexed by
+    /// `[y][x]`. Pixels outside of the symbol are left untouched.
+    pub fn fill_2d<const W: usize, const H: usize>(&self, target: &mut [[bool; W]; H]) {
+        assert!(W >= self.width() as usize, "The target is not wide enough");
+        assert!(H >= self.height() as usize, "The target is not high enough");
+
+        for (line, target) in self.lines().zip(target.iter_mut()) {
+            for (bit, pixel) in line.zip(target.iter_mut()) {
+                *pixel = bit;
+            }
+        }
+    }
+
+    /// Returns an iterator over the rendered lines packed as bytes (MSB
+    /// first). Each line yields `ceil(width / 8)` bytes, the unused bits of
+    /// the last byte are set to 0.
+    pub fn row_bytes(&self) -> impl Iterator<Item = impl Iterator<Item = u8> + 'a> + 'a {
+        self.lines().map(|mut line| core::iter::from_fn(move || {
+            let mut byte = 0;
+            let mut count = 0;
+            for bit in line.by_ref().take(8) {
+                byte |= (bit as u8) << (7 - count);
+                count += 1;
+            }
+            (count > 0).then_some(byte)
+        }))
+    }
+
+    /// Returns the rendering as text, one line per line of pixels ending with
+    /// a newline, the pixels being drawn with `on` and `off` (e.g. '█' and
+    /// ' '). The scale, quiet zone and inversion settings are applied.
+    #[cfg(feature = "alloc")]
+    pub fn to_ascii_art(&self, on: char, off: char) -> alloc::string::String {
+        let mut art = alloc::string::String::new();
+        for line in self.lines() {
+            art.extend(line.map(|bit| if bit { on } else { off }));
+            art.push('\n');
+        }
+        art
+    }
+
+    /// Renders the PDF417 at each of the `scales` in a single pass over the
+    /// modules, returning one buffer of `width * height` pixels (line by
+    /// line) per scale. Each buffer is the same as the [bits](PDF417Render::bits)
+    /// of this render with the corresponding [set_scale](PDF417Render::set_scale),
+    /// the other settings (quiet zone, inversion...) being kept. Panics if a
+    /// scale is 0 on any axis.
+    #[cfg(feature = "alloc")]
+    pub fn render_scales(&self, scales: &[(u16, u16)]) -> alloc::vec::Vec<alloc::vec::Vec<bool>> {
+        use alloc::vec::Vec;
+        assert!(scales.iter().all(|&(sx, sy)| sx > 0 && sy > 0), "The scale must be at least 1 on both axes");
+
+        let base = self.clone().set_scale((1, 1));
+        let (width, height) = (base.width() as usize, base.height() as usize);
+        let quiet = self.quiet_zone as usize;
+        let mut outputs: Vec<Vec<bool>> = scales.iter()
+            .map(|&(sx, sy)| Vec::with_capacity(width * sx as usize * height * sy as usize))
+            .collect();
+
+        let mut line = Vec::with_capacity(width);
+        for (y, bits) in base.lines().enumerate() {
+            line.clear();
+            line.extend(bits);
+            // the quiet zone is as high as it is wide
+            let is_quiet = y < quiet || y >= height - quiet;
+            for (output, &(sx, sy)) in outputs.iter_mut().zip(scales) {
+                let start = output.len();
+                output.extend(line.iter().flat_map(|&bit| core::iter::repeat(bit).take(sx as usize)));
+                let end = output.len();
+                for _ in 1..if is_quiet { sx } else { sy } {
+                    output.extend_from_within(start..end);
+                }
+            }
+        }
+        outputs
+    }
+
+    /// Same as [fill_bitmap](PDF417Render::fill_bitmap) but returns a
+    /// [SizeError] if the target does not hold exactly
+    /// `ceil(width / 8) * height` bytes.
+    pub fn try_fill_bitmap(&self, target: &mut [u8]) -> Result<(), SizeError> {
+        let expected = (self.width() as usize + 7) / 8 * self.height() as usize;
+        if target.len() != expected {
+            return Err(SizeError { expected, actual: target.len() });
+        }
+        self.fill_bitmap(target);
+        Ok(())
+    }
+
+    /// Renders the PDF417 into a 1 bit per pixel bitmap (MSB first). Each
+    /// line starts on a new byte, i.e. the stride is `ceil(width / 8)` bytes.
+    /// The quiet zone is part of the bitmap and is inverted along with the
+    /// symbol. Panics if the target holds less than
+    /// `ceil(width / 8) * height` bytes, see
+    /// [try_fill_bitmap](PDF417Render::try_fill_bitmap).
+    pub fn fill_bitmap(&self, target: &mut [u8]) {
+        self.fill_bitmap_strided(target, (self.width() as usize + 7) / 8);
+    }
+
+    /// Same as [fill_bitmap](PDF417Render::fill_bitmap) but each line starts
+    /// `row_stride_bytes` bytes after the previous one (e.g. rows aligned on
+    /// 4 bytes). The padding bytes after `ceil(width / 8)` bytes are left
+    /// untouched. The stride must be at least `ceil(width / 8)` bytes and
+    /// the target must hold all the lines (the padding of the last line
+    /// excepted).
+    pub fn fill_bitmap_strided(&self, target: &mut [u8], row_stride_bytes: usize) {
+        let line_bytes = (self.width() as usize + 7) / 8;
+        assert!(row_stride_bytes >= line_bytes,
+            "The row stride must be at least ceil(width / 8) bytes");
+        assert!(target.len() >= (self.height() as usize - 1) * row_stride_bytes + line_bytes,
+            "The target is too small to hold the bitmap");
+        for (line, target) in self.row_bytes().zip(target.chunks_mut(row_stride_bytes)) {
+            for (byte, t) in line.zip(target.iter_mut()) {
+                *t |= byte;
             }
         }
     }
 }
 
+/// Returns the pixels (like [bits](PDF417Render::bits)) of the symbols `a`
+/// and `b` placed side by side with a gap of `gap` pixels between them,
+/// producing a single raster `a.width() + gap + b.width()` pixels wide. The
+/// gap is filled with the background color of `a`. Both symbols must have
+/// the same height.
+pub fn join_horizontal<'a, A, B>(a: &PDF417Render<'a, A>, b: &PDF417Render<'a, B>, gap: u32) -> impl Iterator<Item = bool> + 'a
+where A: Row<'a> + 'a, B: Row<'a> + 'a {
+    assert_eq!(a.height(), b.height(), "The joined symbols must have the same height");
+    let background = a.inverted;
+    a.lines().zip(b.lines())
+        .flat_map(move |(left, right)| left.chain(core::iter::repeat(background).take(gap as usize)).chain(right))
+}
+
 #[cfg(feature = "embedded-graphics")]
 impl<'a, R> embedded_graphics::Drawable for PDF417Render<'a, R>
 where
@@ -217,3 +1031,881 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::string::ToString;
+
+    use super::ConfigError;
+    use crate::{PDF417, TruncatedPDF417, MicroPDF417, PDF417Encoder, Variant, RegularVariant};
+    use crate::{pdf417_width, pdf417_height, m_pdf417_width};
+    use crate::generators::{row::Row, PDF417Row, TruncatedPDF417Row, MicroPDF417Row};
+
+    #[test]
+    fn test_try_new_storage_size() {
+        let storage = [0u16; 30];
+        let err = PDF417::try_new(&storage, 7, 4, 0).err().unwrap();
+        assert_eq!(err, ConfigError::StorageSize { rows: 7, cols: 4, actual: 30 });
+        assert_eq!(err.to_string(), "expected 28 codewords (7x4), got 30");
+        assert!(PDF417::try_new(&storage[..28], 7, 4, 0).is_ok());
+    }
+
+    #[test]
+    fn test_try_new_matches_config() {
+        let storage = [0u16; 3];
+        let err = PDF417::try_new(&storage, 3, 1, 8).err().unwrap();
+        assert_eq!(err, ConfigError::EccOverflow { ecc: 512, capacity: 3 });
+
+        let storage = [0u16; 31 * 30];
+        let err = PDF417::try_new(&storage, 31, 30, 0).err().unwrap();
+        assert_eq!(err, ConfigError::TooLarge { rows: 31, cols: 30 });
+
+        for (rows, cols, level) in [(3, 1, 0), (3, 2, 2), (4, 3, 3), (31, 30, 0), (90, 30, 8)] {
+            let storage = [0u16; 90 * 30];
+            let storage = &storage[..rows as usize * cols as usize];
+            assert_eq!(PDF417::try_new(storage, rows, cols, level).err(),
+                RegularVariant::try_new(rows, cols, level).err());
+        }
+    }
+
+    #[test]
+    fn test_from_config() {
+        let err = RegularVariant::try_new(31, 30, 0).err().unwrap();
+        assert_eq!(err.to_string(), "31x30 codewords exceed the maximum of 928 codewords");
+        let err = RegularVariant::try_new(3, 2, 2).err().unwrap();
+        assert_eq!(err.to_string(), "8 ECC codewords do not fit in a symbol of 6 codewords");
+
+        let config = RegularVariant::try_new(5, 3, 1).unwrap();
+        let mut storage = [0u16; 5 * 3];
+        PDF417Encoder::new(&mut storage, false).append_ascii("Config").seal(1);
+        let expected = PDF417::new(&storage, 5, 3, 1);
+        assert!(PDF417::from_config(&storage, config).bits().eq(expected.bits()));
+        assert!(TruncatedPDF417::from_config(&storage, config).bits()
+            .eq(TruncatedPDF417::new(&storage, 5, 3, 1).bits()));
+    }
+
+    #[test]
+    fn test_try_set_scale() {
+        let storage = [900u16; 3 * 2];
+        let render = PDF417::new(&storage, 3, 2, 0).render();
+        let err = render.clone().try_set_scale((0, 1)).err().unwrap();
+        assert_eq!(err, ConfigError::InvalidScale(0, 1));
+        assert_eq!(err.to_string(), "invalid scale (0, 1) (must be at least 1 on both axes)");
+        assert!(render.clone().try_set_scale((2, 0)).is_err());
+        assert_eq!(render.try_set_scale((2, 3)).unwrap().scale(), (2, 3));
+    }
+
+    #[test]
+    fn test_data_region() {
+        let storage = [900u16; 5 * 3];
+        let render = PDF417::new(&storage, 5, 3, 0).render().set_scale((2, 3)).set_quiet_zone(2);
+        let (x, y, w, h) = render.data_region();
+        assert_eq!((x, y, w, h), (4 + (17 + 17) * 2, 4, 3 * 17 * 2, 5 * 3));
+        // the right row indicator and the end pattern follow the data region
+        assert_eq!(x + w + (17 + 18) * 2 + 4, render.width());
+
+        let render = TruncatedPDF417::new(&storage, 5, 3, 0).render().set_scale((2, 3));
+        assert_eq!(render.data_region().2, 3 * 17 * 2);
+
+        let variant = Variant::with_dimensions(8, 2).unwrap();
+        let storage = [900u16; 8 * 2];
+        let render = MicroPDF417::from_variant(&storage, variant).render();
+        assert_eq!(render.data_region(), (10, 0, 2 * 17, 8 * 2));
+    }
+
+    #[test]
+    fn test_truncated_unreliable() {
+        let storage = [900u16; 10 * 3];
+        assert!(!TruncatedPDF417::new(&storage, 10, 3, 0).is_unreliable());
+        let storage = [900u16; 30 * 3];
+        assert!(!TruncatedPDF417::new(&storage, 30, 3, 0).is_unreliable());
+        let storage = [900u16; 60 * 3];
+        assert!(TruncatedPDF417::new(&storage, 60, 3, 0).is_unreliable());
+    }
+
+    #[test]
+    fn test_quiet_zone() {
+        const COLS: u8 = 2;
+        const ROWS: u8 = 3;
+        const W: usize = pdf417_width!(COLS, 2) + 2 * 2 * 2;
+        const H: usize = pdf417_height!(ROWS, 2) + 2 * 2 * 2;
+
+        let mut input = [0u16; (COLS * ROWS) as usize];
+        let (level, _) = PDF417Encoder::new(&mut input, false)
+            .append_ascii("Quiet").fit_seal().unwrap();
+        let render = PDF417::new(&input, ROWS, COLS, level).render()
+            .set_scale((2, 2)).set_quiet_zone(2);
+        assert_eq!((render.width() as usize, render.height() as usize), (W, H));
+
+        let mut pixels = [0u8; W * H];
+        render.fill_with_quiet_zone(&mut pixels, &1, &2, &3);
+
+        let mut symbol = [false; pdf417_width!(COLS, 2) * pdf417_height!(ROWS, 2)];
+        PDF417::new(&input, ROWS, COLS, level).render().set_scale((2, 2)).fill_bits(&mut symbol);
+        let symbol_width = pdf417_width!(COLS, 2);
+
+        for (i, &p) in pixels.iter().enumerate() {
+            let (x, y) = (i % W, i / W);
+            if !(4..W - 4).contains(&x) || !(4..H - 4).contains(&y) {
+                assert_eq!(p, 3, "({x}, {y}) is not in the quiet zone");
+            } else {
+                let bit = symbol[(y - 4) * symbol_width + x - 4];
+                assert_eq!(p, if bit { 1 } else { 2 }, "({x}, {y})");
+            }
+        }
+
+        // without a quiet zone color, the quiet zone is off
+        let mut bits = [true; W * H];
+        render.fill_bits(&mut bits);
+        assert!(pixels.iter().zip(bits).all(|(&p, b)| b == (p == 1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "number of codewords of the MicroPDF417 variant")]
+    fn test_from_variant_regular_storage() {
+        let mut input = [0u16; 3 * 4];
+        let encoder = PDF417Encoder::new(&mut input, false).append_ascii("Regular");
+        assert!(!encoder.is_micro());
+        let (_, sealed) = encoder.fit_seal().unwrap();
+        MicroPDF417::from_variant(sealed, Variant::with_dimensions(11, 1).unwrap());
+    }
+
+    #[test]
+    fn test_modules_to_pixels() {
+        use super::modules_to_pixels;
+        // 10 mils at 300 DPI: 3 pixels per module
+        assert_eq!(modules_to_pixels(69, 10.0, 300.0), 207);
+        // 15 mils at 203 DPI: 3.045 pixels rounded to 3
+        assert_eq!(modules_to_pixels(10, 15.0, 203.0), 30);
+        // 7.5 mils at 203 DPI: 1.52 pixels rounded to 2
+        assert_eq!(modules_to_pixels(10, 7.5, 203.0), 20);
+        // never less than a pixel per module
+        assert_eq!(modules_to_pixels(10, 1.0, 72.0), 10);
+
+        let storage = [3, 900, 900];
+        let render = PDF417::new(&storage, 3, 1, 0).render().set_scale((1, 3)).set_quiet_zone(2);
+        assert_eq!(render.print_scale(10.0, 300.0), (3, 9));
+        assert_eq!(render.print_size(10.0, 300.0), (pdf417_width!(1, 3) as u32 + 12, 3 * 9 + 12));
+        let scale = render.print_scale(10.0, 300.0);
+        let render = render.set_scale(scale);
+        assert_eq!(render.print_size(10.0, 300.0), (render.width(), render.height()));
+    }
+
+    #[test]
+    fn test_render_with() {
+        use super::RenderConfig;
+        const CONFIG: RenderConfig = RenderConfig::new().set_scale((2, 6)).set_quiet_zone(2);
+
+        let mut input = [0u16; 3 * 3];
+        let (level, _) = PDF417Encoder::new(&mut input, false)
+            .append_ascii("Config").fit_seal().unwrap();
+
+        let pdf417 = PDF417::new(&input, 3, 3, level);
+        let expected = pdf417.clone().render().set_scale((2, 6)).set_quiet_zone(2);
+        let render = pdf417.clone().render_with(CONFIG);
+        assert_eq!((render.width(), render.height()), (expected.width(), expected.height()));
+        assert!(render.bits().eq(expected.bits()));
+
+        let render = pdf417.clone().render_with(CONFIG.set_inverted(true));
+        assert!(render.bits().eq(expected.set_inverted(true).bits()));
+
+        let render = pdf417.clone().render_with(RenderConfig::default());
+        assert!(render.bits().eq(pdf417.render().bits()));
+    }
+
+    #[test]
+    fn test_is_effectively_empty() {
+        let mut input = [0u16; 3 * 3];
+        let (level, _) = PDF417Encoder::new(&mut input, false).fit_seal().unwrap();
+        assert!(PDF417::new(&input, 3, 3, level).is_effectively_empty());
+
+        let mut input = [0u16; 3 * 3];
+        let (level, _) = PDF417Encoder::new(&mut input, false)
+            .append_ascii("Data").fit_seal().unwrap();
+        assert!(!PDF417::new(&input, 3, 3, level).is_effectively_empty());
+
+        let v = Variant::with_dimensions(11, 1).unwrap();
+        let mut input = [0u16; 11];
+        PDF417Encoder::new(&mut input, true).seal(v.into());
+        assert!(MicroPDF417::from_variant(&input, v).is_effectively_empty());
+        PDF417Encoder::new(&mut input, true).append_num(1).seal(v.into());
+        assert!(!MicroPDF417::from_variant(&input, v).is_effectively_empty());
+    }
+
+    #[test]
+    fn test_row_range() {
+        const COLS: u8 = 2;
+        const ROWS: u8 = 9;
+        const W: usize = pdf417_width!(COLS, 2);
+
+        let mut input = [0u16; (COLS * ROWS) as usize];
+        let (level, _) = PDF417Encoder::new(&mut input, false)
+            .append_ascii("Pagination").fit_seal().unwrap();
+        let render = PDF417::new(&input, ROWS, COLS, level).render().set_scale((2, 3));
+
+        let mut full = [false; W * pdf417_height!(ROWS, 3)];
+        render.fill_bits(&mut full);
+
+        let top = render.clone().set_row_range(0, 4);
+        let bottom = render.clone().set_row_range(4, ROWS);
+        assert_eq!(top.row_range(), (0, 4));
+        assert_eq!(top.height() as usize, pdf417_height!(4, 3));
+        assert_eq!(bottom.height() as usize, pdf417_height!(ROWS - 4, 3));
+        assert!(top.bits().chain(bottom.bits()).eq(full.iter().copied()));
+    }
+
+    #[test]
+    #[should_panic(expected = "The row range must be within the rows of the PDF417")]
+    fn test_row_range_invalid() {
+        let storage = [3, 900, 900];
+        PDF417::new(&storage, 3, 1, 0).render().set_row_range(2, 4);
+    }
+
+    #[test]
+    fn test_inverted_quiet_zone_bitmap() {
+        const COLS: u8 = 2;
+        const ROWS: u8 = 3;
+        // 8 pixels of quiet zone on each side
+        const W: usize = pdf417_width!(COLS) + 16;
+        const H: usize = pdf417_height!(ROWS, 3) + 16;
+        const STRIDE: usize = (W + 7) / 8;
+
+        let mut input = [0u16; (COLS * ROWS) as usize];
+        let (level, _) = PDF417Encoder::new(&mut input, false)
+            .append_ascii("IQ").fit_seal().unwrap();
+        let render = PDF417::new(&input, ROWS, COLS, level).render()
+            .set_scale((1, 3)).set_quiet_zone(8);
+
+        let mut bitmap = [0u8; STRIDE * H];
+        render.clone().fill_bitmap(&mut bitmap);
+        let mut inverted = [0u8; STRIDE * H];
+        render.set_inverted(true).fill_bitmap(&mut inverted);
+
+        // top lines are quiet zone only, padding bits excluded
+        let last_mask = !(0xFFu8 >> (W % 8));
+        assert!(bitmap[..8 * STRIDE].iter().all(|&b| b == 0));
+        for line in inverted[..8 * STRIDE].chunks(STRIDE) {
+            assert!(line[..STRIDE - 1].iter().all(|&b| b == 0xFF));
+            assert_eq!(line[STRIDE - 1], last_mask);
+        }
+        // left quiet zone byte of a symbol line, then the start pattern bar
+        assert_eq!(bitmap[8 * STRIDE], 0x00);
+        assert_eq!(inverted[8 * STRIDE], 0xFF);
+        assert_eq!(bitmap[8 * STRIDE + 1] & 0x80, 0x80);
+        assert_eq!(inverted[8 * STRIDE + 1] & 0x80, 0x00);
+        // bottom right corner
+        assert_eq!(inverted[STRIDE * H - 1], last_mask);
+    }
+
+    #[test]
+    fn test_row_width() {
+        for cols in 1..=30 {
+            assert_eq!(PDF417Row::width((3, cols)) as usize, pdf417_width!(cols));
+            assert_eq!(TruncatedPDF417Row::width((3, cols)) as usize, pdf417_width!(cols, 1, true));
+        }
+
+        let storage = [0u16; 176];
+        for v in 0..34 {
+            let v = Variant::from(v);
+            let size = v.rows() as usize * v.cols() as usize;
+            assert_eq!(MicroPDF417Row::width((v.rows(), v.cols())) as usize, m_pdf417_width!(v.cols()));
+
+            let render = MicroPDF417::from_variant(&storage[..size], v).render();
+            assert_eq!(render.width() as usize, m_pdf417_width!(v.cols()));
+            assert!(render.lines().all(|line| line.count() == render.width() as usize));
+        }
+    }
+
+    #[test]
+    fn test_truncated_width() {
+        const COLS: u8 = 3;
+        const ROWS: u8 = 3;
+        let mut input = [0u16; (COLS * ROWS) as usize];
+        let (level, _) = PDF417Encoder::new(&mut input, false)
+            .append_ascii("Truncated").fit_seal().unwrap();
+
+        for scale in 1..=4u16 {
+            let render = TruncatedPDF417::new(&input, ROWS, COLS, level).render()
+                .set_scale((scale, 1));
+            assert_eq!(render.width() as usize, pdf417_width!(COLS, scale, true));
+            for line in render.lines() {
+                let line: std::vec::Vec<bool> = line.collect();
+                assert_eq!(line.len(), pdf417_width!(COLS, scale, true));
+                // the start pattern begins with a bar, the symbol ends with the stop bar
+                assert!(line[0] && line[line.len() - scale as usize..].iter().all(|&b| b));
+            }
+        }
+    }
+
+    #[test]
+    fn test_try_fill() {
+        use super::SizeError;
+        const W: usize = pdf417_width!(1);
+        const H: usize = pdf417_height!(3);
+
+        let storage = [3, 900, 900];
+        let render = PDF417::new(&storage, 3, 1, 0).render();
+
+        let mut bits = [false; W * H + 1];
+        assert_eq!(render.try_fill_bits(&mut bits), Err(SizeError { expected: W * H, actual: W * H + 1 }));
+        assert_eq!(render.try_fill_bits(&mut bits[..W * H - 1]).unwrap_err().expected, W * H);
+        assert!(bits.iter().all(|&b| !b), "the target must be left untouched");
+        assert_eq!(render.try_fill_bits(&mut bits[..W * H]), Ok(()));
+
+        let mut bitmap = [0u8; (W + 7) / 8 * H + 1];
+        let err = render.try_fill_bitmap(&mut bitmap).unwrap_err();
+        assert_eq!(err.to_string(), std::format!("the target must hold exactly {} elements, got {}", (W + 7) / 8 * H, bitmap.len()));
+        assert_eq!(render.try_fill_bitmap(&mut bitmap[..(W + 7) / 8 * H]), Ok(()));
+    }
+
+    #[test]
+    fn test_fill_2d() {
+        const COLS: u8 = 2;
+        const ROWS: u8 = 3;
+        const W: usize = pdf417_width!(COLS, 2);
+        const H: usize = pdf417_height!(ROWS, 2);
+
+        let mut input = [0u16; (COLS * ROWS) as usize];
+        let (level, _) = PDF417Encoder::new(&mut input, false)
+            .append_ascii("2D").fit_seal().unwrap();
+        let render = PDF417::new(&input, ROWS, COLS, level).render().set_scale((2, 2));
+
+        let mut bits = [false; W * H];
+        render.fill_bits(&mut bits);
+
+        let mut target = [[true; W + 3]; H + 1];
+        render.fill_2d(&mut target);
+        for (y, line) in target.iter().enumerate() {
+            for (x, &pixel) in line.iter().enumerate() {
+                if x < W && y < H {
+                    assert_eq!(pixel, bits[y * W + x]);
+                } else {
+                    assert!(pixel, "pixel outside of the symbol was modified");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_row_bytes() {
+        const COLS: u8 = 2;
+        const ROWS: u8 = 3;
+        const SCALE: (u16, u16) = (8, 1);
+        const W: usize = pdf417_width!(COLS, SCALE.0);
+        const H: usize = pdf417_height!(ROWS, SCALE.1);
+
+        let mut input = [0u16; (COLS * ROWS) as usize];
+        let (level, _) = PDF417Encoder::new(&mut input, false)
+            .append_ascii("Row").fit_seal().unwrap();
+        let render = PDF417::new(&input, ROWS, COLS, level).render().set_scale(SCALE);
+        assert_eq!(W % 8, 0);
+
+        let mut bitmap = [0u8; W / 8 * H];
+        render.fill_bitmap(&mut bitmap);
+
+        let mut rows = [0u8; W / 8 * H];
+        let mut i = 0;
+        for line in render.row_bytes() {
+            let start = i;
+            for byte in line {
+                rows[i] = byte;
+                i += 1;
+            }
+            assert_eq!(i - start, W / 8);
+        }
+        assert_eq!(i, rows.len());
+        assert_eq!(rows, bitmap);
+
+        let mut bits = [false; W * H];
+        render.fill_bits(&mut bits);
+        for (i, &bit) in bits.iter().enumerate() {
+            assert_eq!(bit, bitmap[i / 8] & (0x80 >> (i % 8)) != 0);
+        }
+    }
+
+    #[test]
+    fn test_codewords_positioned() {
+        let storage: [u16; 12] = core::array::from_fn(|i| i as u16 * 10);
+        let pdf417 = PDF417::new(&storage, 3, 4, 0);
+
+        let mut count = 0;
+        for (i, (row, col, cw)) in pdf417.codewords_positioned().enumerate() {
+            assert_eq!((row, col, cw), ((i / 4) as u8, (i % 4) as u8, storage[i]));
+            count += 1;
+        }
+        assert_eq!(count, 12);
+        assert_eq!(pdf417.codewords_positioned().nth(6), Some((1, 2, 60)));
+    }
+
+    #[test]
+    fn test_custom_patterns() {
+        use crate::{generators::bitfield::Bitfield, START_PATTERN, END_PATTERN};
+        const W: usize = pdf417_width!(1);
+        const H: usize = pdf417_height!(3);
+
+        let storage = [3, 900, 900];
+        let mut default = [false; W * H];
+        PDF417::new(&storage, 3, 1, 0).render().fill_bits(&mut default);
+
+        let mut standard = [false; W * H];
+        PDF417::new(&storage, 3, 1, 0).set_patterns(START_PATTERN, END_PATTERN)
+            .render().fill_bits(&mut standard);
+        assert_eq!(default, standard);
+
+        let start = Bitfield::new(0b11111110010101000, 17);
+        let end = Bitfield::new(0b111111101000100101, 18);
+        let mut custom = [false; W * H];
+        PDF417::new(&storage, 3, 1, 0).set_patterns(start, end)
+            .render().fill_bits(&mut custom);
+        assert_ne!(default, custom);
+        for (line, default) in custom.chunks(W).zip(default.chunks(W)) {
+            assert!(line[..17].iter().copied().eq(start));
+            assert!(line[W - 18..].iter().copied().eq(end));
+            assert_eq!(line[17..W - 18], default[17..W - 18]);
+        }
+    }
+
+    #[test]
+    fn test_info() {
+        let storage = [0u16; 5 * 3];
+        let info = PDF417::new(&storage, 5, 3, 2).render().set_scale((2, 4)).info();
+        assert_eq!(info, super::SymbolInfo {
+            width: 120 * 2,
+            height: 5 * 4,
+            rows: 5,
+            cols: 3,
+            modules: 120 * 5,
+            data_codewords: 15 - 8,
+            ecc_codewords: 8,
+            aspect_ratio: 12.0,
+        });
+
+        let variant = crate::Variant::with_dimensions(8, 2).unwrap();
+        let storage = [0u16; 8 * 2];
+        let info = crate::MicroPDF417::from_variant(&storage, variant).render().info();
+        assert_eq!((info.data_codewords, info.ecc_codewords), (8, 8));
+    }
+
+    #[test]
+    fn test_fill_bitmap_strided() {
+        const COLS: u8 = 2;
+        const ROWS: u8 = 3;
+        const WIDTH: usize = pdf417_width!(COLS);
+        const TIGHT: usize = (WIDTH + 7) / 8;
+        const STRIDE: usize = (TIGHT + 3) / 4 * 4 + 4;
+
+        let storage = [900u16; (ROWS * COLS) as usize];
+        let render = PDF417::new(&storage, ROWS, COLS, 0).render();
+
+        let mut tight = [0u8; TIGHT * ROWS as usize];
+        render.fill_bitmap(&mut tight);
+        let mut strided = [0u8; STRIDE * ROWS as usize];
+        for row in strided.chunks_mut(STRIDE) {
+            row[TIGHT..].fill(0xAA);
+        }
+        render.fill_bitmap_strided(&mut strided, STRIDE);
+
+        for (row, expected) in strided.chunks(STRIDE).zip(tight.chunks(TIGHT)) {
+            assert_eq!(&row[..TIGHT], expected);
+            assert!(row[TIGHT..].iter().all(|&b| b == 0xAA));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The row stride must be at least")]
+    fn test_fill_bitmap_strided_too_small() {
+        let storage = [900u16; 6];
+        let render = PDF417::new(&storage, 3, 2, 0).render();
+        let mut target = [0u8; 64];
+        render.fill_bitmap_strided(&mut target, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "The target is too small to hold the bitmap")]
+    fn test_fill_bitmap_too_small() {
+        let storage = [900u16; 6];
+        let render = PDF417::new(&storage, 3, 2, 0).render();
+        let size = (render.width() as usize + 7) / 8 * render.height() as usize;
+        let mut target = [0u8; 1024];
+        render.fill_bitmap(&mut target[..size - 1]);
+    }
+
+    #[test]
+    fn test_join_horizontal() {
+        let storage = [900u16; 3 * 2];
+        let a = PDF417::new(&storage, 3, 2, 0).render();
+        let b = TruncatedPDF417::new(&storage, 3, 2, 0).render();
+        let gap = 5;
+        let width = (a.width() + gap + b.width()) as usize;
+
+        let mut joined = [false; 3 * 512];
+        let mut count = 0;
+        for (pixel, bit) in joined.iter_mut().zip(super::join_horizontal(&a, &b, gap)) {
+            *pixel = bit;
+            count += 1;
+        }
+        assert_eq!(count, width * a.height() as usize);
+
+        for ((line, left), right) in joined[..count].chunks(width).zip(a.lines()).zip(b.lines()) {
+            let (l, rest) = line.split_at(a.width() as usize);
+            let (g, r) = rest.split_at(gap as usize);
+            assert!(l.iter().copied().eq(left));
+            assert!(g.iter().all(|&p| !p));
+            assert!(r.iter().copied().eq(right));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The joined symbols must have the same height")]
+    fn test_join_horizontal_height_mismatch() {
+        let storage = [900u16; 4 * 2];
+        let a = PDF417::new(&storage[..6], 3, 2, 0).render();
+        let b = PDF417::new(&storage, 4, 2, 0).render();
+        let _ = super::join_horizontal(&a, &b, 0);
+    }
+
+    #[test]
+    fn test_text_band() {
+        let storage = [900u16; 3 * 2];
+        let render = PDF417::new(&storage, 3, 2, 0).render().set_scale((2, 3)).set_quiet_zone(1);
+        let banded = render.clone().set_text_band(4);
+        assert_eq!(banded.height(), render.height() + 4 * 3);
+        assert_eq!(banded.width(), render.width());
+        assert_eq!(banded.text_band_origin(), (2, 2 + 3 * 3));
+
+        let width = render.width() as usize;
+        let (_, y) = banded.text_band_origin();
+        assert!(banded.lines().zip(render.lines()).take(y as usize).all(|(a, b)| a.eq(b)));
+        let mut band = banded.lines().skip(y as usize);
+        for _ in 0..4 * 3 {
+            assert_eq!(band.next().unwrap().filter(|&bit| !bit).count(), width);
+        }
+        // bottom quiet zone
+        assert_eq!(band.count(), 2);
+
+        let mut target = [0u8; 256 * 32];
+        banded.fill_with_quiet_zone(&mut target, &1, &2, &3);
+        let band_start = (y as usize + 1) * width;
+        assert_eq!(&target[band_start..band_start + 3], &[3, 3, 2]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_render_scales() {
+        use super::CalibrationSpec;
+        let storage = [900u16; 3 * 2];
+        let render = PDF417::new(&storage, 3, 2, 0).render()
+            .set_quiet_zone(2)
+            .set_text_band(1)
+            .with_calibration_bars(CalibrationSpec { solid_rows: 1, blank_rows: 0, striped_rows: 1 });
+        let scales = [(1, 1), (2, 2), (4, 3)];
+        let outputs = render.render_scales(&scales);
+        assert_eq!(outputs.len(), scales.len());
+        for (output, &scale) in outputs.iter().zip(&scales) {
+            let standalone = render.clone().set_scale(scale);
+            assert_eq!(output.len(), standalone.total_bits());
+            assert!(output.iter().copied().eq(standalone.bits()));
+        }
+
+        let inverted = render.set_inverted(true).set_scale((2, 2));
+        assert!(inverted.render_scales(&[(2, 2)])[0].iter().copied().eq(inverted.bits()));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_to_ascii_art() {
+        let storage = [900u16; 3 * 2];
+        let render = PDF417::new(&storage, 3, 2, 0).render().set_scale((1, 2)).set_quiet_zone(2);
+        let art = render.to_ascii_art('#', '.');
+        assert!(art.ends_with('\n'));
+        assert_eq!(art.lines().count(), render.height() as usize);
+        for (line, bits) in art.lines().zip(render.lines()) {
+            assert_eq!(line.chars().count(), render.width() as usize);
+            assert!(line.chars().eq(bits.map(|bit| if bit { '#' } else { '.' })));
+        }
+        assert!(art.starts_with(".."));
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut storage = [0u16; 4 * 3];
+        PDF417Encoder::new(&mut storage, false).append_ascii("Valid").seal(1);
+        assert_eq!(PDF417::new(&storage, 4, 3, 1).validate(), Ok(()));
+        assert_eq!(TruncatedPDF417::new(&storage, 4, 3, 1).validate(), Ok(()));
+        // sealed with a different level
+        let err = PDF417::new(&storage, 4, 3, 2).validate().err().unwrap();
+        assert_eq!(err, ConfigError::LengthMismatch { expected: 4, actual: 8 });
+
+        storage[0] = 5;
+        let err = PDF417::new(&storage, 4, 3, 1).validate().err().unwrap();
+        assert_eq!(err, ConfigError::LengthMismatch { expected: 8, actual: 5 });
+        assert_eq!(err.to_string(), "the length codeword must be 8, got 5");
+
+        // 512 ECC codewords do not fit in 3x1 codewords
+        let storage = [3u16; 3];
+        let err = PDF417::try_new(&storage, 3, 1, 8).err().unwrap();
+        assert_eq!(err, ConfigError::EccOverflow { ecc: 512, capacity: 3 });
+    }
+
+    #[test]
+    #[should_panic(expected = "The ECC codewords must fit in the symbol")]
+    fn test_new_ecc_overflow() {
+        let storage = [3u16; 3];
+        PDF417::new(&storage, 3, 1, 8);
+    }
+
+    #[test]
+    fn test_diff_rows() {
+        let mut a = [0u16; 6 * 3];
+        PDF417Encoder::new(&mut a, false).append_ascii("Same text").seal(1);
+        let mut b = a;
+        assert_eq!(PDF417::new(&a, 6, 3, 1).render().diff_rows(&PDF417::new(&b, 6, 3, 1).render()).count(), 0);
+
+        // the 8th codeword is in the 3rd row (index 2)
+        b[7] = (b[7] + 1) % 900;
+        let (ra, rb) = (PDF417::new(&a, 6, 3, 1).render(), PDF417::new(&b, 6, 3, 1).render());
+        assert!(ra.diff_rows(&rb).eq([2]));
+
+        b[16] = (b[16] + 1) % 900;
+        let rb = PDF417::new(&b, 6, 3, 1).render();
+        assert!(ra.diff_rows(&rb).eq([2, 5]));
+    }
+
+    #[test]
+    fn test_fill_with_knockout() {
+        const COLS: u8 = 3;
+        const ROWS: u8 = 10;
+        const WIDTH: usize = pdf417_width!(COLS);
+        const HEIGHT: usize = ROWS as usize;
+
+        let mut storage = [0u16; (ROWS * COLS) as usize];
+        PDF417Encoder::new(&mut storage, false).append_ascii("Logo").seal(3);
+        let render = PDF417::new(&storage, ROWS, COLS, 3).render();
+
+        let mut normal = [0u8; WIDTH * HEIGHT];
+        render.fill(&mut normal, &1, &0);
+        let mut knocked = [0u8; WIDTH * HEIGHT];
+        render.fill_with_knockout(&mut knocked, &1, &0, (40, 2, 10, 3), &7);
+
+        for (i, (&k, &n)) in knocked.iter().zip(normal.iter()).enumerate() {
+            let (x, y) = (i % WIDTH, i / WIDTH);
+            if (40..50).contains(&x) && (2..5).contains(&y) {
+                assert_eq!(k, 7);
+            } else {
+                assert_eq!(k, n);
+            }
+        }
+        assert_eq!(knocked.iter().filter(|&&p| p == 7).count(), 30);
+    }
+
+    #[test]
+    fn test_fill_bits_interlaced() {
+        const COLS: u8 = 2;
+        const ROWS: u8 = 5;
+        const WIDTH: usize = pdf417_width!(COLS);
+        const HEIGHT: usize = pdf417_height!(ROWS, 3);
+
+        let mut storage = [0u16; (ROWS * COLS) as usize];
+        PDF417Encoder::new(&mut storage, false).append_ascii("Fields").seal(0);
+        let render = PDF417::new(&storage, ROWS, COLS, 0).render().set_scale((1, 3));
+
+        let mut full = [false; WIDTH * HEIGHT];
+        render.fill_bits(&mut full);
+        let mut even = [false; WIDTH * (HEIGHT + 1) / 2];
+        let mut odd = [false; WIDTH * (HEIGHT + 1) / 2];
+        render.fill_bits_interlaced(&mut even, &mut odd);
+
+        let mut merged = [false; WIDTH * HEIGHT];
+        for (y, line) in merged.chunks_mut(WIDTH).enumerate() {
+            let field = if y % 2 == 0 { &even } else { &odd };
+            line.copy_from_slice(&field[(y / 2) * WIDTH..(y / 2 + 1) * WIDTH]);
+        }
+        assert_eq!(merged, full);
+    }
+
+    #[test]
+    fn test_rle_rows() {
+        const COLS: u8 = 3;
+        const ROWS: u8 = 4;
+        const WIDTH: usize = pdf417_width!(COLS, 2) + 2 * 2 * 2;
+        const HEIGHT: usize = pdf417_height!(ROWS, 2) + 2 * 2 * 2;
+
+        let mut storage = [0u16; (ROWS * COLS) as usize];
+        PDF417Encoder::new(&mut storage, false).append_ascii("Runs").seal(0);
+        let render = PDF417::new(&storage, ROWS, COLS, 0).render().set_scale((2, 2)).set_quiet_zone(2);
+
+        let mut expected = [false; WIDTH * HEIGHT];
+        render.fill_bits(&mut expected);
+
+        let mut expanded = [true; WIDTH * HEIGHT];
+        let mut i = 0;
+        let mut lines = 0;
+        for line in render.rle_rows() {
+            let mut previous = None;
+            let mut width = 0;
+            for (value, length) in line {
+                assert_ne!(previous, Some(value), "consecutive runs must differ");
+                previous = Some(value);
+                expanded[i..i + length as usize].fill(value);
+                i += length as usize;
+                width += length;
+            }
+            assert_eq!(width, render.width());
+            lines += 1;
+        }
+        assert_eq!(lines, HEIGHT);
+        assert_eq!(expanded, expected);
+    }
+
+    #[test]
+    fn test_pdf_error() {
+        use super::{PdfError, SizeError};
+        use crate::SealError;
+
+        fn pipeline(storage: &mut [u16], rows: u8, cols: u8, level: u8, target: &mut [bool]) -> Result<(), PdfError> {
+            let storage = PDF417Encoder::new(storage, false).append_ascii("Safe").try_seal(level)?;
+            PDF417::try_new(storage, rows, cols, level)?
+                .render()
+                .try_set_row_range(0, rows)?
+                .try_fill_bits(target)?;
+            Ok(())
+        }
+
+        let mut storage = [0u16; 3 * 4];
+        let mut target = [false; pdf417_width!(4) * 3];
+        assert_eq!(pipeline(&mut storage, 3, 4, 1, &mut target), Ok(()));
+        assert_eq!(pipeline(&mut storage, 3, 4, 9, &mut target), Err(PdfError::Seal(SealError::InvalidLevel(9))));
+        assert_eq!(pipeline(&mut storage, 2, 6, 1, &mut target), Err(PdfError::Config(ConfigError::InvalidRows(2))));
+        let err = pipeline(&mut storage, 3, 4, 1, &mut target[1..]).err().unwrap();
+        assert_eq!(err, PdfError::Size(SizeError { expected: target.len(), actual: target.len() - 1 }));
+        assert_eq!(err.to_string(), std::format!("the target must hold exactly {} elements, got {}", target.len(), target.len() - 1));
+
+        let render = PDF417::new(&storage, 3, 4, 1).render();
+        let err = render.clone().try_set_row_range(2, 4).err().unwrap();
+        assert_eq!(err, ConfigError::RowRange { start: 2, end: 4, rows: 3 });
+        assert_eq!(err.to_string(), "invalid row range [2, 4) for 3 rows");
+        let mut bitmap = [0u8; 4];
+        assert!(render.try_fill_bitmap(&mut bitmap).is_err());
+        let mut pixels = [0u8; 4];
+        assert!(render.try_fill(&mut pixels, &1, &0).is_err());
+    }
+
+    #[test]
+    fn test_symbol_dimensions() {
+        let storage = [900u16; 5 * 3];
+        let pdf417 = PDF417::new(&storage, 5, 3, 0);
+        let dimensions = (pdf417.width(), pdf417.height());
+        let render = pdf417.render().set_scale((1, 1)).set_quiet_zone(0);
+        assert_eq!(dimensions, (render.width(), render.height()));
+        assert_eq!(dimensions, (pdf417_width!(3) as u32, 5));
+
+        let truncated = TruncatedPDF417::new(&storage, 5, 3, 0);
+        let dimensions = (truncated.width(), truncated.height());
+        let render = truncated.render().set_scale((1, 1)).set_quiet_zone(0);
+        assert_eq!(dimensions, (render.width(), render.height()));
+
+        let variant = Variant::with_dimensions(8, 2).unwrap();
+        let storage = [900u16; 8 * 2];
+        let micro = MicroPDF417::from_variant(&storage, variant);
+        let dimensions = (micro.width(), micro.height());
+        let render = micro.render().set_scale((1, 1)).set_quiet_zone(0);
+        assert_eq!(dimensions, (render.width(), render.height()));
+        assert_eq!(dimensions, (variant.width(), variant.height()));
+    }
+
+    #[test]
+    fn test_rows_as_bits() {
+        use crate::{generators::bitfield::Bitfield, low_level, START_PATTERN};
+        const W: usize = pdf417_width!(2);
+        let storage = [900u16; 3 * 2];
+        let pdf417 = PDF417::new(&storage, 3, 2, 0);
+        let rows: [[bool; W]; 3] = {
+            let mut rows = pdf417.rows_as_bits::<W>();
+            [rows.next().unwrap(), rows.next().unwrap(), rows.next().unwrap()]
+        };
+        assert!(rows.iter().flatten().copied().eq(pdf417.bits()));
+
+        // start pattern then the left row indicator (rows (3 - 1) / 3 = 0)
+        let start = START_PATTERN.into_iter();
+        let left = Bitfield::new((1 << 16) | low_level(0, 0) as u32, 17).into_iter();
+        assert!(rows[0][..34].iter().copied().eq(start.chain(left)));
+    }
+
+    #[test]
+    #[should_panic(expected = "W must be equal to the width of the PDF417 in modules")]
+    fn test_rows_as_bits_width_mismatch() {
+        let storage = [900u16; 3 * 2];
+        let _ = PDF417::new(&storage, 3, 2, 0).rows_as_bits::<10>();
+    }
+
+    #[test]
+    fn test_total_bits() {
+        let storage = [900u16; 5 * 3];
+        let pdf417 = PDF417::new(&storage, 5, 3, 0);
+        assert_eq!(pdf417.total_bits(), pdf417.bits().count());
+        let render = pdf417.render().set_scale((2, 3)).set_quiet_zone(2);
+        assert_eq!(render.total_bits(), render.bits().count());
+
+        let truncated = TruncatedPDF417::new(&storage, 5, 3, 0);
+        assert_eq!(truncated.total_bits(), truncated.bits().count());
+        assert!(truncated.total_bits() < PDF417::new(&storage, 5, 3, 0).total_bits());
+
+        let variant = Variant::with_dimensions(8, 2).unwrap();
+        let storage = [900u16; 8 * 2];
+        let micro = MicroPDF417::from_variant(&storage, variant);
+        assert_eq!(micro.total_bits(), micro.bits().count());
+        let render = micro.render();
+        assert_eq!(render.total_bits(), render.bits().count());
+    }
+
+    #[test]
+    fn test_calibration_bars() {
+        use super::CalibrationSpec;
+        let storage = [900u16; 3 * 2];
+        let render = PDF417::new(&storage, 3, 2, 0).render().set_scale((2, 2)).set_quiet_zone(1);
+        let bars = CalibrationSpec { solid_rows: 1, blank_rows: 2, striped_rows: 1 };
+        let calibrated = render.clone().with_calibration_bars(bars);
+        assert_eq!(calibrated.height(), render.height() + 4 * 2);
+        assert_eq!(calibrated.width(), render.width());
+
+        let symbol = render.height() as usize - 2;
+        assert!(calibrated.lines().zip(render.lines()).take(symbol).all(|(a, b)| a.eq(b)));
+
+        let mut lines = calibrated.lines().skip(symbol);
+        let width = pdf417_width!(2, 2);
+        for _ in 0..2 {
+            assert!(lines.next().unwrap().eq((0..width + 4).map(|x| (2..width + 2).contains(&x))));
+        }
+        for _ in 0..4 {
+            assert!(lines.next().unwrap().all(|bit| !bit));
+        }
+        for _ in 0..2 {
+            // modules 2 pixels wide, the first one on
+            assert!(lines.next().unwrap().eq((0..width + 4).map(|x| (2..width + 2).contains(&x) && (x - 2) % 4 < 2)));
+        }
+        // bottom quiet zone
+        assert_eq!(lines.count(), 2);
+    }
+
+    #[test]
+    fn test_correction_capacity() {
+        let storage = [0u16; 20 * 30];
+        for (level, expected) in [(0, (1, 2)), (2, (4, 8)), (5, (32, 64)), (8, (256, 512))] {
+            let render = PDF417::new(&storage, 20, 30, level).render();
+            assert_eq!(render.correction_capacity(), expected);
+        }
+
+        let variant = crate::Variant::with_dimensions(8, 2).unwrap();
+        let storage = [0u16; 8 * 2];
+        let render = crate::MicroPDF417::from_variant(&storage, variant).render();
+        assert_eq!(render.correction_capacity(), (4, 8));
+    }
+
+    #[test]
+    #[should_panic(expected = "The storage length must be equal to rows * cols")]
+    fn test_new_storage_size() {
+        let storage = [0u16; 30];
+        PDF417::new(&storage, 7, 4, 0);
+    }
+}
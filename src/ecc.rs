@@ -1,6 +1,7 @@
-//! ECC generation for PDF417
+//! ECC generation and error correction for PDF417
 
-use crate::tables::*;
+use core::fmt;
+use crate::{tables::*, GF_MODULUS, MAX_SYMBOL_CODEWORDS};
 
 /// Returns the number of ECC codewords required by the specified level (0-8)
 /// of a regular PDF417.
@@ -27,8 +28,44 @@ pub const fn ecc_count(level: u8) -> usize {
 /// The ECC is calculated for the (total-N) first codewords where total is the
 /// length of the codewords slice.
 pub fn generate_ecc(codewords: &mut [u16], level: u8) {
+    let factors = ecc_factors(level);
+
+    generate_with_factors(codewords, factors);
+}
+
+/// Updates in-place the ECC codewords of a slice previously processed by
+/// [generate_ecc] after the data codeword at `changed_index` was changed from
+/// `old_value` to its current value. Reed-Solomon codes being linear, only the
+/// contribution of the change is computed which is faster than a full
+/// [generate_ecc] when the changed codeword is close to the end of the data.
+pub fn update_ecc(codewords: &mut [u16], level: u8, changed_index: usize, old_value: u16) {
+    let factors = ecc_factors(level);
+    assert!(codewords.len() >= factors.len(), "ECC codewords could not fit in buffer");
+
+    let (data, ecc) = codewords.split_at_mut(codewords.len() - factors.len());
+    assert!(changed_index < data.len(), "the changed codeword must be a data codeword");
+
+    let delta = (data[changed_index] % GF_MODULUS + GF_MODULUS - old_value % GF_MODULUS) % GF_MODULUS;
+    if delta == 0 {
+        return;
+    }
+
+    // ECC of the data made of `delta` at `changed_index` and zeros elsewhere
+    let mut buf = [0u16; ECC_L8.len()];
+    let diff = &mut buf[..factors.len()];
+    ecc_step(factors, diff, delta);
+    for _ in changed_index + 1..data.len() {
+        ecc_step(factors, diff, 0);
+    }
+
+    for (e, d) in ecc.iter_mut().zip(diff.iter()) {
+        *e = (*e + (GF_MODULUS - *d) % GF_MODULUS) % GF_MODULUS;
+    }
+}
+
+fn ecc_factors(level: u8) -> &'static [u16] {
     assert!(level <= 8, "ECC level must be between 0 and 8 inclusive");
-    let factors: &'static [u16] = match level {
+    match level {
         0 => &ECC_L0,
         1 => &ECC_L1,
         2 => &ECC_L2,
@@ -39,10 +76,7 @@ pub fn generate_ecc(codewords: &mut [u16], level: u8) {
         7 => &ECC_L7,
         8 => &ECC_L8,
         _ => unreachable!()
-    };
-
-    assert!(codewords.len() >= factors.len(), "ECC codewords could not fit in buffer");
-    generate_ecc_codewords(factors, codewords);
+    }
 }
 
 /// Calculate and stores the ECC codewords in the slice `codewords` in-place.
@@ -53,33 +87,216 @@ pub fn generate_ecc(codewords: &mut [u16], level: u8) {
 pub fn generate_micro_ecc(codewords: &mut [u16], count: usize, k: usize) {
     assert!(count > 0, "count cannot be empty");
     assert!(codewords.len() >= count);
-    generate_ecc_codewords(&ECC_MICRO[k..(k+count)], codewords);
+    generate_with_factors(codewords, &ECC_MICRO[k..(k+count)]);
 }
 
-fn generate_ecc_codewords(factors: &'static [u16], codewords: &mut [u16]) {
+/// Calculate and stores the ECC codewords in the slice `codewords` in-place
+/// using the coefficients `factors` of a custom generator polynomial (in the
+/// same order as the built-in tables). The last `factors.len()` codewords are
+/// overwritten by the ECC codewords. This is the primitive used by
+/// [generate_ecc] and [generate_micro_ecc].
+pub fn generate_with_factors(codewords: &mut [u16], factors: &[u16]) {
+    assert!(codewords.len() >= factors.len(), "ECC codewords could not fit in buffer");
     let (data, ecc) = codewords.split_at_mut(codewords.len() - factors.len());
     ecc.fill(0);
 
     for cw in data {
-        let t = (*cw + ecc[0]) % 929;
-
-        for i in (0..factors.len()).rev() {
-            let factor = ((t as usize * factors[i] as usize) % 929) as u16;
-            let d = if i > 0 { ecc[factors.len() - i] } else { 0 };
-            ecc[factors.len() - 1 - i] = (d + 929 - factor) % 929;
-        }
+        ecc_step(factors, ecc, *cw);
     }
 
     for e in ecc {
         if *e != 0 {
-            *e = 929 - *e;
+            *e = GF_MODULUS - *e;
         }
     }
 }
 
+/// Feeds a data codeword to the ECC division register.
+fn ecc_step(factors: &[u16], ecc: &mut [u16], cw: u16) {
+    let t = (cw + ecc[0]) % GF_MODULUS;
+
+    for i in (0..factors.len()).rev() {
+        let factor = ((t as usize * factors[i] as usize) % GF_MODULUS as usize) as u16;
+        let d = if i > 0 { ecc[factors.len() - i] } else { 0 };
+        ecc[factors.len() - 1 - i] = (d + GF_MODULUS - factor) % GF_MODULUS;
+    }
+}
+
+/// Error returned when the codewords could not be corrected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EccError {
+    /// The codewords contain more errors than the ECC codewords can correct.
+    Uncorrectable,
+}
+
+impl fmt::Display for EccError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EccError::Uncorrectable => write!(f, "too many errors to be corrected"),
+        }
+    }
+}
+
+/// Maximum number of errors correctable by a regular PDF417 (level 8).
+const MAX_ERRORS: usize = ECC_L8.len() / 2;
+
+/// Outcome of [decode_detailed]: the number of corrected codewords and their
+/// positions in the codewords slice.
+#[derive(Debug, Clone)]
+pub struct Correction {
+    corrected: usize,
+    positions: [u16; MAX_ERRORS],
+}
+
+impl Correction {
+    /// Returns the number of corrected codewords.
+    pub const fn corrected(&self) -> usize {
+        self.corrected
+    }
+
+    /// Returns the positions of the corrected codewords in ascending order.
+    /// At most `ecc_count(level) / 2` positions are reported.
+    pub fn positions(&self) -> &[u16] {
+        &self.positions[..self.corrected]
+    }
+}
+
+/// Corrects in-place the errors of the codewords of a regular PDF417
+/// protected with the ECC level `level` (see [generate_ecc]) and returns the
+/// number of corrected codewords. See [decode_detailed] for the positions.
+pub fn decode(codewords: &mut [u16], level: u8) -> Result<usize, EccError> {
+    decode_detailed(codewords, level).map(|c| c.corrected())
+}
+
+/// Corrects in-place the errors of the codewords of a regular PDF417
+/// protected with the ECC level `level` (see [generate_ecc]). Up to
+/// `ecc_count(level) / 2` erroneous codewords can be corrected, the returned
+/// [Correction] reports their positions which is useful to detect marginal
+/// scans. The codewords must be below [GF_MODULUS].
+pub fn decode_detailed(codewords: &mut [u16], level: u8) -> Result<Correction, EccError> {
+    let k = ecc_factors(level).len();
+    assert!(codewords.len() > k, "ECC codewords could not fit in buffer");
+    assert!(codewords.len() <= MAX_SYMBOL_CODEWORDS, "a PDF417 can not contain more than 928 codewords");
+
+    let mut correction = Correction { corrected: 0, positions: [0; MAX_ERRORS] };
+
+    // S(x) = sum of r(3^(j+1)) x^j
+    let mut syndromes = [0u16; ECC_L8.len()];
+    let syndromes = &mut syndromes[..k];
+    let mut has_errors = false;
+    for (j, s) in syndromes.iter_mut().enumerate() {
+        let x = gf_pow(3, j + 1);
+        *s = codewords.iter().fold(0, |acc, &cw| gf_add(gf_mul(acc, x), cw % GF_MODULUS));
+        has_errors |= *s != 0;
+    }
+    if !has_errors {
+        return Ok(correction);
+    }
+
+    // Berlekamp-Massey: error locator L(x) = prod(1 - X_i x)
+    let mut locator = [0u16; MAX_ERRORS * 2 + 1];
+    let mut prev = [0u16; MAX_ERRORS * 2 + 1];
+    let mut tmp = [0u16; MAX_ERRORS * 2 + 1];
+    let (locator, prev, tmp) = (&mut locator[..=k], &mut prev[..=k], &mut tmp[..=k]);
+    locator[0] = 1;
+    prev[0] = 1;
+    let (mut len, mut shift, mut last) = (0, 1, 1);
+    for n in 0..k {
+        let delta = (1..=len).fold(syndromes[n], |acc, i| gf_add(acc, gf_mul(locator[i], syndromes[n - i])));
+        if delta == 0 {
+            shift += 1;
+            continue;
+        }
+
+        let coef = gf_mul(delta, gf_inv(last));
+        tmp.copy_from_slice(locator);
+        for i in shift..=k {
+            locator[i] = gf_sub(locator[i], gf_mul(coef, prev[i - shift]));
+        }
+        if 2 * len <= n {
+            len = n + 1 - len;
+            prev.copy_from_slice(tmp);
+            last = delta;
+            shift = 1;
+        } else {
+            shift += 1;
+        }
+    }
+    if len > k / 2 {
+        return Err(EccError::Uncorrectable);
+    }
+
+    // Omega(x) = S(x) L(x) mod x^k
+    let mut evaluator = [0u16; ECC_L8.len()];
+    let evaluator = &mut evaluator[..k];
+    for (i, e) in evaluator.iter_mut().enumerate() {
+        *e = (0..=i.min(len)).fold(0, |acc, j| gf_add(acc, gf_mul(locator[j], syndromes[i - j])));
+    }
+
+    // Chien search and Forney algorithm
+    let n = codewords.len();
+    for (pos, cw) in codewords.iter_mut().enumerate() {
+        let x_inv = gf_inv(gf_pow(3, n - 1 - pos));
+        if gf_eval(&locator[..=len], x_inv) != 0 {
+            continue;
+        }
+
+        // formal derivative of L evaluated at x_inv
+        let derivative = (1..=len).fold(0, |acc, i| {
+            gf_add(acc, gf_mul(gf_mul(i as u16, locator[i]), gf_pow(x_inv, i - 1)))
+        });
+        if derivative == 0 || correction.corrected == len {
+            return Err(EccError::Uncorrectable);
+        }
+        let error = gf_mul(gf_eval(evaluator, x_inv), gf_inv(derivative));
+        *cw = gf_add(*cw % GF_MODULUS, error);
+        correction.positions[correction.corrected] = pos as u16;
+        correction.corrected += 1;
+    }
+    if correction.corrected != len {
+        return Err(EccError::Uncorrectable);
+    }
+
+    Ok(correction)
+}
+
+fn gf_add(a: u16, b: u16) -> u16 {
+    ((a as u32 + b as u32) % GF_MODULUS as u32) as u16
+}
+
+fn gf_sub(a: u16, b: u16) -> u16 {
+    ((a as u32 + GF_MODULUS as u32 - b as u32) % GF_MODULUS as u32) as u16
+}
+
+fn gf_mul(a: u16, b: u16) -> u16 {
+    ((a as u32 * b as u32) % GF_MODULUS as u32) as u16
+}
+
+fn gf_pow(mut a: u16, mut e: usize) -> u16 {
+    let mut r = 1;
+    while e > 0 {
+        if e & 1 == 1 {
+            r = gf_mul(r, a);
+        }
+        a = gf_mul(a, a);
+        e >>= 1;
+    }
+    r
+}
+
+fn gf_inv(a: u16) -> u16 {
+    // 929 is prime: a^(929-2) = a^-1
+    gf_pow(a, 927)
+}
+
+/// Evaluates the polynomial (lowest degree first) at `x`.
+fn gf_eval(poly: &[u16], x: u16) -> u16 {
+    poly.iter().rev().fold(0, |acc, &c| gf_add(gf_mul(acc, x), c))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{generate_ecc, ecc_count};
+    use super::{generate_ecc, generate_with_factors, update_ecc, ecc_count, decode, decode_detailed, EccError};
 
     const INPUT_DATA: [u16; 16] = [16, 902, 1, 278, 827, 900, 295, 902, 2, 326, 823, 544, 900, 149, 900, 900];
 
@@ -163,4 +380,90 @@ mod tests {
         generate_ecc(&mut data, 8);
         assert_eq!(data[INPUT_DATA.len()..], EXPECTED);
     }
+
+    #[test]
+    fn test_generate_with_factors() {
+        for level in [0, 4, 8] {
+            let mut expected = [0u16; INPUT_DATA.len() + ecc_count(8)];
+            let expected = &mut expected[..INPUT_DATA.len() + ecc_count(level)];
+            expected[..INPUT_DATA.len()].copy_from_slice(&INPUT_DATA);
+            let mut data = [0u16; INPUT_DATA.len() + ecc_count(8)];
+            let data = &mut data[..expected.len()];
+            data.copy_from_slice(expected);
+
+            generate_ecc(expected, level);
+            generate_with_factors(data, super::ecc_factors(level));
+            assert_eq!(data, expected);
+        }
+    }
+
+    #[test]
+    fn test_update_ecc() {
+        for level in [0, 3, 5] {
+            let mut data = [0u16; INPUT_DATA.len() + ecc_count(5)];
+            let len = INPUT_DATA.len() + ecc_count(level);
+            let data = &mut data[..len];
+            data[..INPUT_DATA.len()].copy_from_slice(&INPUT_DATA);
+            generate_ecc(data, level);
+
+            for (index, value) in [(1, 900), (15, 12), (0, 16), (7, 928), (15, 900), (3, 0)] {
+                let old_value = data[index];
+                data[index] = value;
+                update_ecc(data, level, index, old_value);
+
+                let mut expected = [0u16; INPUT_DATA.len() + ecc_count(5)];
+                let expected = &mut expected[..len];
+                expected[..INPUT_DATA.len()].copy_from_slice(&data[..INPUT_DATA.len()]);
+                generate_ecc(expected, level);
+                assert_eq!(data, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_without_errors() {
+        let mut data = [0u16; INPUT_DATA.len() + ecc_count(2)];
+        data[..INPUT_DATA.len()].copy_from_slice(&INPUT_DATA);
+        generate_ecc(&mut data, 2);
+        let expected = data;
+        assert_eq!(decode(&mut data, 2), Ok(0));
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn test_decode_detailed() {
+        for level in [0, 2, 4] {
+            let mut data = [0u16; INPUT_DATA.len() + ecc_count(4)];
+            let len = INPUT_DATA.len() + ecc_count(level);
+            let data = &mut data[..len];
+            data[..INPUT_DATA.len()].copy_from_slice(&INPUT_DATA);
+            generate_ecc(data, level);
+
+            let mut expected = [0u16; INPUT_DATA.len() + ecc_count(4)];
+            expected[..len].copy_from_slice(data);
+
+            // inject the maximum number of errors at known positions
+            let positions = [3, 7, 10, 16, 17, 20, 25, 29, 30, 33, 35, 38, 40, 41, 42, 47];
+            let positions = &positions[..ecc_count(level) / 2];
+            for &p in positions {
+                data[p] = (data[p] + 100 + p as u16) % 929;
+            }
+
+            let correction = decode_detailed(data, level).unwrap();
+            assert_eq!(correction.corrected(), positions.len());
+            assert!(correction.positions().iter().map(|&p| p as usize).eq(positions.iter().copied()));
+            assert_eq!(data, &expected[..len]);
+        }
+    }
+
+    #[test]
+    fn test_decode_too_many_errors() {
+        let mut data = [0u16; INPUT_DATA.len() + ecc_count(1)];
+        data[..INPUT_DATA.len()].copy_from_slice(&INPUT_DATA);
+        generate_ecc(&mut data, 1);
+        for p in [0, 5, 9] {
+            data[p] = (data[p] + 1) % 929;
+        }
+        assert_eq!(decode(&mut data, 1), Err(EccError::Uncorrectable));
+    }
 }
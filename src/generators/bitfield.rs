@@ -46,8 +46,8 @@ impl iter::Iterator for Bits {
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.count > 0 {
-            let bit = (self.value >> self.count) & 1 != 0;
             self.count -= 1;
+            let bit = (self.value >> self.count) & 1 != 0;
             Some(bit)
         } else {
             None
@@ -75,3 +75,42 @@ impl iter::DoubleEndedIterator for Bits {
 
 impl iter::ExactSizeIterator for Bits {}
 impl iter::FusedIterator for Bits {}
+
+#[cfg(test)]
+mod tests {
+    use super::Bitfield;
+
+    #[test]
+    fn test_iter_order() {
+        let field = Bitfield::new(0b1011_0001, 8);
+        assert!(field.into_iter().eq([true, false, true, true, false, false, false, true]));
+        assert!(field.into_iter().rev().eq([true, false, false, false, true, true, false, true]));
+        assert_eq!(field.into_iter().len(), 8);
+        assert_eq!(field.as_pair(), (0b1011_0001, 8));
+    }
+
+    #[test]
+    fn test_iter_full_width() {
+        // 24 bits, the maximum size, with the highest and lowest bits set
+        let field = Bitfield::new(0x80_0001, 24);
+        let mut bits = field.into_iter();
+        assert_eq!(bits.next(), Some(true));
+        assert_eq!(bits.len(), 23);
+        assert!(bits.by_ref().take(22).all(|bit| !bit));
+        assert_eq!(bits.next(), Some(true));
+        assert_eq!(bits.next(), None);
+        assert_eq!(Bitfield::new(0, 0).into_iter().next(), None);
+    }
+
+    #[test]
+    fn test_iter_mixed_directions() {
+        let mut bits = Bitfield::new(0b11001, 5).into_iter();
+        assert_eq!(bits.next(), Some(true));
+        assert_eq!(bits.next_back(), Some(true));
+        assert_eq!(bits.next(), Some(true));
+        assert_eq!(bits.next_back(), Some(false));
+        assert_eq!(bits.next(), Some(false));
+        assert_eq!(bits.next(), None);
+        assert_eq!(bits.next_back(), None);
+    }
+}
@@ -1,6 +1,6 @@
 use core::iter;
 
-use crate::{HL_TO_LL, M_PDF417_VARIANTS_COUNT, M_PDF417_RAP, M_PDF417_SIDE, M_PDF417_CENTER};
+use crate::{low_level, M_PDF417_VARIANTS_COUNT, M_PDF417_RAP, M_PDF417_SIDE, M_PDF417_CENTER, Variant};
 use crate::generators::{row::{Row, FixedSize}, bitfield::Bitfield};
 
 macro_rules! cw {
@@ -8,7 +8,7 @@ macro_rules! cw {
         M_PDF417_SIDE[$val as usize] as u32
     };
     ($tb:expr, $val:expr) => {
-        Bitfield::new((1 << 16) | HL_TO_LL[$tb as usize][$val as usize] as u32, 17)
+        Bitfield::new((1 << 16) | low_level($tb, $val) as u32, 17)
     }
 }
 
@@ -21,6 +21,23 @@ enum RowPattern {
     None,
 }
 
+/// Returns the indices (start, center, end, table) used by the row `row` of
+/// a MicroPDF417 of the variant `variant` (see [Variant]): the indices of the
+/// left, center and right row address patterns and the cluster table
+/// (0-2) of the data codewords. Useful to lay out the rows in a custom
+/// renderer.
+pub fn micro_rap_indices(variant: u8, row: u8) -> (u8, u8, u8, u8) {
+    let v = Variant::from(variant);
+    assert!(row < v.rows(), "The row must be within the rows of the MicroPDF417 variant {variant}");
+    rap_indices(MicroPDF417Row::prepare((v.rows(), v.cols()), variant), row)
+}
+
+/// Offsets the initial indices of the variant (see [MicroPDF417Row::prepare])
+/// to the row `row`.
+const fn rap_indices(infos: (u8, u8, u8, u8), row: u8) -> (u8, u8, u8, u8) {
+    ((infos.0 + row) % 51, (infos.1 + row) % 51, (infos.2 + row) % 51, (infos.3 + row) % 3)
+}
+
 #[derive(Clone)]
 pub struct MicroPDF417Row<'a> {
     codewords: &'a [u16],
@@ -37,9 +54,13 @@ impl<'a> FixedSize for MicroPDF417Row<'a> {}
 impl<'a> Row<'a> for MicroPDF417Row<'a> {
     type Info = (u8, u8, u8, u8);
     const DEFAULT_SCALE: (u16, u16) = (1, 2);
+    const DATA_OFFSET: usize = 0;
 
-    fn prepare(_dimensions: (u8, u8), variant: u8) -> Self::Info {
-        // TODO: assert that dimensions are the same as the variant
+    #[allow(clippy::identity_op, clippy::erasing_op)]
+    fn prepare(dimensions: (u8, u8), variant: u8) -> Self::Info {
+        let v = Variant::from(variant);
+        assert_eq!(dimensions, (v.rows(), v.cols()),
+            "The dimensions (rows, cols) do not match the MicroPDF417 variant {variant}");
         (
             M_PDF417_RAP[0 * M_PDF417_VARIANTS_COUNT + variant as usize] - 1,
             M_PDF417_RAP[1 * M_PDF417_VARIANTS_COUNT + variant as usize] - 1,
@@ -49,19 +70,29 @@ impl<'a> Row<'a> for MicroPDF417Row<'a> {
     }
 
     fn init(codewords: &'a [u16], row: u8, infos: Self::Info) -> Self {
+        let (start_ind, center_ind, end_ind, table) = rap_indices(infos, row);
         Self {
             codewords,
             next_pat: RowPattern::Start,
 
-            start_ind: (infos.0 + row) % 51,
-            center_ind: (infos.1 + row) % 51, 
-            end_ind: (infos.2 + row) % 51,
-            table: (infos.3 + row) % 3
+            start_ind,
+            center_ind,
+            end_ind,
+            table,
         }
     }
 
+    fn ecc_count(variant: u8) -> usize {
+        Variant::from(variant).ecc_count()
+    }
+
     fn width(dimensions: (u8, u8)) -> u32 {
-        /* start */ 10 + dimensions.0 as u32 * 17 + /* middle */ if dimensions.0 > 2 { 10 } else { 0 } + /* end */ 11
+        /* start */ 10 + dimensions.1 as u32 * 17 + /* middle */ if dimensions.1 > 2 { 10 } else { 0 } + /* end */ 11
+    }
+
+    fn data_region(dimensions: (u8, u8)) -> (u32, u32) {
+        // the center row address pattern splits the data columns in two
+        (/* start */ 10, dimensions.1 as u32 * 17 + /* middle */ if dimensions.1 > 2 { 10 } else { 0 })
     }
 }
 
@@ -97,15 +128,62 @@ impl<'a> iter::Iterator for MicroPDF417Row<'a> {
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        if let RowPattern::None = self.next_pat {
-            (0, Some(0))
-        } else {
-            let len = self.codewords.len();
-            let count = 2 + len + (len >> 1) & 1; // if len > 2 then +1
-            (count, Some(count))
-        }
+        let len = self.codewords.len();
+        let count = match self.next_pat {
+            RowPattern::Start if len > 2 => 1 + len + 2, // start + center + end
+            RowPattern::Start => 1 + len + 1, // start + end
+            RowPattern::LeftData => len + 2, // center + end
+            RowPattern::RightData => len + 1, // end
+            RowPattern::None => 0,
+        };
+        (count, Some(count))
     }
 }
 
 impl<'a> ExactSizeIterator for MicroPDF417Row<'a> {}
 impl<'a> iter::FusedIterator for MicroPDF417Row<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::MicroPDF417Row;
+    use crate::{generators::row::Row, Variant};
+
+    #[test]
+    fn test_prepare_matching_variant() {
+        let v = Variant::with_dimensions(4, 4).unwrap();
+        MicroPDF417Row::prepare((v.rows(), v.cols()), v.variant());
+    }
+
+    #[test]
+    #[should_panic(expected = "do not match the MicroPDF417 variant 0")]
+    fn test_prepare_mismatched_variant() {
+        MicroPDF417Row::prepare((4, 4), 0);
+    }
+
+    #[test]
+    fn test_micro_rap_indices() {
+        use super::micro_rap_indices;
+        // variant 0 (11x1): left 1, center 1, right 9 (1-based), table 0
+        assert_eq!(micro_rap_indices(0, 0), (0, 0, 8, 0));
+        assert_eq!(micro_rap_indices(0, 1), (1, 1, 9, 1));
+        assert_eq!(micro_rap_indices(0, 10), (10, 10, 18, 1));
+
+        let v = Variant::from(12);
+        let infos = MicroPDF417Row::prepare((v.rows(), v.cols()), v.variant());
+        for row in 0..v.rows() {
+            let r = MicroPDF417Row::init(&[], row, infos);
+            assert_eq!(micro_rap_indices(12, row), (r.start_ind, r.center_ind, r.end_ind, r.table));
+        }
+    }
+
+    #[test]
+    fn test_row_size_hint() {
+        let codewords = [0u16; 4];
+        for (rows, cols) in [(11, 1), (8, 2), (8, 3), (8, 4)] {
+            let v = Variant::with_dimensions(rows, cols as u8).unwrap();
+            let infos = MicroPDF417Row::prepare((v.rows(), v.cols()), v.variant());
+            let row = MicroPDF417Row::init(&codewords[..cols], 0, infos);
+            assert_eq!(row.len(), row.count());
+        }
+    }
+}
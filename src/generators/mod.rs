@@ -5,4 +5,5 @@ pub mod micro_pdf417;
 
 pub type PDF417Row<'a> = pdf417::PDF417Row<'a, false>;
 pub type TruncatedPDF417Row<'a> = pdf417::PDF417Row<'a, true>;
-pub use micro_pdf417::MicroPDF417Row;
+pub use self::pdf417::{RowPatternKind, CompactPDF417Row};
+pub use micro_pdf417::{MicroPDF417Row, micro_rap_indices};
@@ -1,10 +1,10 @@
 use core::iter;
-use crate::{HL_TO_LL, START_PATTERN, END_PATTERN};
+use crate::{low_level, START_PATTERN, END_PATTERN};
 use crate::generators::{row::{FreeSize, Row}, bitfield::Bitfield};
 
 macro_rules! cw {
     ($tb:expr, $val:expr) => {
-        Bitfield::new((1 << 16) | HL_TO_LL[$tb as usize][$val as usize] as u32, 17)
+        Bitfield::new((1 << 16) | low_level($tb, $val) as u32, 17)
     }
 }
 
@@ -19,13 +19,30 @@ enum RowPattern {
     None,
 }
 
+/// Structural element of a PDF417 row, see [PDF417Row::annotated].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowPatternKind {
+    /// Start pattern
+    Start,
+    /// Left row indicator
+    Left,
+    /// Data codeword
+    Data,
+    /// Right row indicator (omitted by truncated rows)
+    Right,
+    /// End pattern (single stop bar for truncated rows)
+    End,
+}
+
 #[derive(Clone)]
 pub struct PDF417Row<'a, const TRUNCATED: bool> {
     codewords: &'a [u16],
     next_pat: RowPattern,
     table: u8,
     /// (left, right)
-    markers: (u16, u16)
+    markers: (u16, u16),
+    /// (start, end)
+    patterns: (Bitfield, Bitfield)
 }
 
 impl<'a, const TRUNCATED: bool> PDF417Row<'a, TRUNCATED> {
@@ -51,11 +68,39 @@ impl<'a, const TRUNCATED: bool> PDF417Row<'a, TRUNCATED> {
             codewords,
             table,
             markers: (left as u16 + row_id, right as u16 + row_id),
+            patterns: (START_PATTERN, END_PATTERN),
             next_pat: RowPattern::Start
         }
     }
 }
 
+impl<'a, const TRUNCATED: bool> PDF417Row<'a, TRUNCATED> where Self: Iterator<Item = Bitfield> {
+    /// Returns an iterator over the patterns of the row labeled with the
+    /// structural element they correspond to. Useful to debug a malformed
+    /// symbol.
+    pub fn annotated(mut self) -> impl Iterator<Item = (RowPatternKind, Bitfield)> + 'a {
+        iter::from_fn(move || {
+            let kind = match self.next_pat {
+                RowPattern::Start => RowPatternKind::Start,
+                RowPattern::Left => RowPatternKind::Left,
+                RowPattern::Data => RowPatternKind::Data,
+                RowPattern::Right => RowPatternKind::Right,
+                RowPattern::End => RowPatternKind::End,
+                RowPattern::None => return None,
+            };
+            self.next().map(|pattern| (kind, pattern))
+        })
+    }
+
+    /// Returns an iterator over the values of the patterns of the row (start
+    /// pattern, row indicators, 17-bit codeword patterns and end pattern)
+    /// without expanding them to modules, the most significant bit being the
+    /// leftmost module.
+    pub fn pattern_values(self) -> impl Iterator<Item = u32> + 'a {
+        self.map(|pattern| pattern.bits())
+    }
+}
+
 impl<'a, const TRUNCATED: bool> FreeSize for PDF417Row<'a, TRUNCATED> {}
 
 impl<'a> Row<'a> for PDF417Row<'a, false> {
@@ -73,6 +118,10 @@ impl<'a> Row<'a> for PDF417Row<'a, false> {
     fn width(dimensions: (u8, u8)) -> u32 {
         START_PATTERN.size() as u32 + /* left */ 17 + dimensions.1 as u32 * 17 + /* right */ 17 + END_PATTERN.size() as u32
     }
+
+    fn set_patterns(&mut self, start: Bitfield, end: Bitfield) {
+        self.patterns = (start, end);
+    }
 }
 
 impl<'a> iter::Iterator for PDF417Row<'a, false> {
@@ -80,7 +129,7 @@ impl<'a> iter::Iterator for PDF417Row<'a, false> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let (item, next) = match self.next_pat {
-            RowPattern::Start => (Some(START_PATTERN), RowPattern::Left),
+            RowPattern::Start => (Some(self.patterns.0), RowPattern::Left),
             RowPattern::Left => (Some(cw!(self.table, self.markers.0)), RowPattern::Data),
             RowPattern::Data => {
                 let cw = self.codewords[0];
@@ -91,7 +140,7 @@ impl<'a> iter::Iterator for PDF417Row<'a, false> {
                 (Some(cw!(self.table, cw)), next)
             },
             RowPattern::Right => (Some(cw!(self.table, self.markers.1)), RowPattern::End),
-            RowPattern::End => (Some(END_PATTERN), RowPattern::None),
+            RowPattern::End => (Some(self.patterns.1), RowPattern::None),
             RowPattern::None => (None, RowPattern::None)
         };
 
@@ -130,6 +179,11 @@ impl<'a> Row<'a> for PDF417Row<'a, true> {
     fn width(dimensions: (u8, u8)) -> u32 {
         START_PATTERN.size() as u32 + /* left */ 17 + dimensions.1 as u32 * 17 + /* end */ 1
     }
+
+    fn set_patterns(&mut self, start: Bitfield, _end: Bitfield) {
+        // truncated rows end with a single stop bar instead of the end pattern
+        self.patterns.0 = start;
+    }
 }
 
 impl<'a> iter::Iterator for PDF417Row<'a, true> {
@@ -137,7 +191,7 @@ impl<'a> iter::Iterator for PDF417Row<'a, true> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let (item, next) = match self.next_pat {
-            RowPattern::Start => (Some(START_PATTERN), RowPattern::Left),
+            RowPattern::Start => (Some(self.patterns.0), RowPattern::Left),
             RowPattern::Left => (Some(cw!(self.table, self.markers.0)), RowPattern::Data),
             RowPattern::Data => {
                 let cw = self.codewords[0];
@@ -169,3 +223,128 @@ impl<'a> iter::Iterator for PDF417Row<'a, true> {
 
 impl<'a> ExactSizeIterator for PDF417Row<'a, true> {}
 impl<'a> iter::FusedIterator for PDF417Row<'a, true> {}
+
+/// Row of a compact PDF417: a regular row keeping the right row indicator
+/// but ending with the single stop bar of truncated rows, which saves 17
+/// modules per row. **Note**: this layout is not part of the specification,
+/// readers looking for the end pattern will not decode it and readers of
+/// truncated PDF417s may read the right row indicator as an extra data
+/// codeword. Make sure your readers support it before using it.
+#[derive(Clone)]
+pub struct CompactPDF417Row<'a>(PDF417Row<'a, false>);
+
+impl<'a> FreeSize for CompactPDF417Row<'a> {}
+
+impl<'a> Row<'a> for CompactPDF417Row<'a> {
+    type Info = (u8, u8, u8);
+    const DEFAULT_SCALE: (u16, u16) = (1, 1);
+
+    fn prepare(dimensions: (u8, u8), level: u8) -> Self::Info {
+        PDF417Row::<false>::prepare(dimensions, level)
+    }
+
+    fn init(codewords: &'a [u16], row: u8, infos: (u8, u8, u8)) -> Self {
+        Self(PDF417Row::new(codewords, row, infos))
+    }
+
+    fn width(dimensions: (u8, u8)) -> u32 {
+        START_PATTERN.size() as u32 + /* left */ 17 + dimensions.1 as u32 * 17 + /* right */ 17 + /* end */ 1
+    }
+
+    fn set_patterns(&mut self, start: Bitfield, _end: Bitfield) {
+        // compact rows end with a single stop bar instead of the end pattern
+        self.0.patterns.0 = start;
+    }
+}
+
+impl<'a> iter::Iterator for CompactPDF417Row<'a> {
+    type Item = Bitfield;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.0.next_pat {
+            RowPattern::End => {
+                self.0.next_pat = RowPattern::None;
+                Some(Bitfield::new(1, 1))
+            },
+            _ => self.0.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for CompactPDF417Row<'a> {}
+impl<'a> iter::FusedIterator for CompactPDF417Row<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::RowPatternKind::{self, *};
+    use crate::generators::{row::Row, PDF417Row, TruncatedPDF417Row};
+    use crate::{low_level, START_PATTERN, END_PATTERN};
+
+    #[test]
+    fn test_annotated() {
+        let codewords = [900, 900];
+        let infos = PDF417Row::prepare((3, 2), 0);
+        let row = PDF417Row::init(&codewords, 0, infos);
+        let mut kinds = [Start; 6];
+        let mut count = 0;
+        for (((kind, pattern), p), k) in row.clone().annotated().zip(row).zip(kinds.iter_mut()) {
+            assert_eq!(pattern.as_pair(), p.as_pair());
+            *k = kind;
+            count += 1;
+        }
+        assert_eq!(count, 6);
+        assert_eq!(kinds, [Start, Left, Data, Data, Right, End]);
+
+        let infos = TruncatedPDF417Row::prepare((3, 2), 0);
+        let row = TruncatedPDF417Row::init(&codewords, 0, infos);
+        let expected: [RowPatternKind; 5] = [Start, Left, Data, Data, End];
+        assert!(row.annotated().map(|(kind, _)| kind).eq(expected));
+    }
+
+    #[test]
+    fn test_compact_row() {
+        use super::CompactPDF417Row;
+        let codewords = [42, 43];
+        let infos = PDF417Row::prepare((3, 2), 0);
+        let full = PDF417Row::init(&codewords, 1, infos);
+        let compact = CompactPDF417Row::init(&codewords, 1, infos);
+        assert_eq!(compact.len(), full.len());
+
+        // same patterns except the end pattern replaced by a stop bar
+        let mut values = compact.clone().map(|pattern| pattern.bits());
+        assert!(values.by_ref().take(5).eq(full.pattern_values().take(5)));
+        assert_eq!(values.next(), Some(1));
+        assert_eq!(values.next(), None);
+        assert_eq!(compact.map(|pattern| pattern.size() as u32).sum::<u32>(), CompactPDF417Row::width((3, 2)));
+
+        let storage = [900u16; 3 * 2];
+        let full = crate::PDF417::new(&storage, 3, 2, 0).render().width();
+        let compact = crate::CompactPDF417::new(&storage, 3, 2, 0).render().width();
+        let truncated = crate::TruncatedPDF417::new(&storage, 3, 2, 0).render().width();
+        assert!(truncated < compact && compact < full);
+    }
+
+    #[test]
+    fn test_pattern_values() {
+        let codewords = [42];
+        // 3 rows, 1 column, level 0: both indicators of the first row are 0
+        let infos = PDF417Row::prepare((3, 1), 0);
+        let row = PDF417Row::init(&codewords, 0, infos);
+        let expected = [
+            START_PATTERN.bits(),
+            (1 << 16) | low_level(0, 0) as u32,
+            (1 << 16) | low_level(0, 42) as u32,
+            (1 << 16) | low_level(0, 0) as u32,
+            END_PATTERN.bits(),
+        ];
+        assert!(row.pattern_values().eq(expected));
+
+        let infos = TruncatedPDF417Row::prepare((3, 1), 0);
+        let row = TruncatedPDF417Row::init(&codewords, 0, infos);
+        assert!(row.pattern_values().eq([expected[0], expected[1], expected[2], 1]));
+    }
+}
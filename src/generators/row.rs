@@ -7,8 +7,27 @@ pub trait FixedSize {}
 pub trait Row<'a>: iter::Iterator<Item = Bitfield> + Clone {
     type Info: Copy; // info must be cheap to copy
     const DEFAULT_SCALE: (u16, u16);
+    /// Number of codewords preceding the data codewords (the length codeword
+    /// of a regular PDF417).
+    const DATA_OFFSET: usize = 1;
 
     fn prepare(dimensions: (u8, u8), level: u8) -> Self::Info;
     fn init(codewords: &'a [u16], row: u8, infos: Self::Info) -> Self;
     fn width(dimensions: (u8, u8)) -> u32;
+    /// Returns the offset and the width in modules of the data columns of a
+    /// row (excluding the start, stop and row indicator patterns), defaults
+    /// to a regular PDF417 row starting with the start pattern and the left
+    /// row indicator.
+    fn data_region(dimensions: (u8, u8)) -> (u32, u32) {
+        (crate::START_PATTERN.size() as u32 + /* left */ 17, dimensions.1 as u32 * 17)
+    }
+    /// Number of ECC codewords for the level (or variant) of the symbol,
+    /// defaults to the ECC codewords of a regular PDF417.
+    fn ecc_count(level: u8) -> usize {
+        crate::ecc::ecc_count(level)
+    }
+
+    /// Overrides the start and stop patterns of the row. Rows that do not
+    /// use the PDF417 start and stop patterns ignore this call.
+    fn set_patterns(&mut self, _start: Bitfield, _end: Bitfield) {}
 }
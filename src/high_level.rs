@@ -1,6 +1,7 @@
 //! User data to high level encoding conversion functions
 
-use crate::{ecc, Variant};
+use core::fmt;
+use crate::{ecc, Variant, RegularVariant, BASE, GF_MODULUS, MAX_SYMBOL_CODEWORDS};
 
 use awint_core::{InlAwi, Bits};
 type U160 = InlAwi<160, { Bits::unstable_raw_digits(160) }>;
@@ -35,9 +36,94 @@ pub const ECI_GENERAL_ID: u16 = 926;
 pub const ECI_CODE_PAGE: u16 = 927;
 // 928: Block start for PDF macro
 
+/// Codewords reserved for the Macro PDF417 control block.
+const MACRO_CODEWORDS: [u16; 3] = [922, 923, 928];
+
 /// Codeword used as padding at the end of the data section
 pub const CW_PADDING: u16 = M_LATCH_TEXT;
 
+/// Encoding modes (and text sub-modes) used by [PDF417Encoder].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Mode {
+    /// Text compaction, uppercase letters
+    Upper = 0,
+    /// Text compaction, lowercase letters
+    Lower = 1,
+    /// Text compaction, digits and some punctuation
+    Mixed = 2,
+    /// Text compaction, punctuation
+    Punctuation = 3,
+    /// Numeric compaction
+    Numeric = 4,
+    /// Byte compaction
+    Byte = 5,
+}
+
+impl Mode {
+    /// Returns true for the sub-modes of the text compaction.
+    pub const fn is_text(self) -> bool {
+        matches!(self, Mode::Upper | Mode::Lower | Mode::Mixed | Mode::Punctuation)
+    }
+}
+
+/// Operation of an encoding plan executed literally by
+/// [PDF417Encoder::append_plan].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op<'d> {
+    /// Latch to the text compaction ([M_LATCH_TEXT]).
+    LatchText,
+    /// Latch to the byte compaction ([M_LATCH_BYTE]).
+    LatchByte,
+    /// Latch to the byte compaction for a multiple of 6 bytes
+    /// ([M_LATCH_BYTE_M6]).
+    LatchByteM6,
+    /// Latch to the numeric compaction ([M_LATCH_NUMERIC]).
+    LatchNumeric,
+    /// Byte shift ([M_SHIFT_BYTE]) followed by the byte.
+    ShiftByte(u8),
+    /// Text values (0 to 29, including the sub-mode latches and shifts)
+    /// packed two per codeword, an odd number of values is padded with 29.
+    Text(&'d [u8]),
+    /// ASCII digits packed in groups of 44 digits without latch.
+    Digits(&'d [u8]),
+    /// Bytes packed 6 per 5 codewords (the remaining bytes one per codeword)
+    /// without latch.
+    Bytes(&'d [u8]),
+    /// Codewords appended as is.
+    Raw(&'d [u16]),
+}
+
+/// Cost of the latches between the modes used by [PDF417Encoder], indexed
+/// by `[from as usize][to as usize]` (see [Mode]). The cost is expressed in
+/// text values, a codeword holding two of them: latches between text
+/// sub-modes cost 1 or 2 values, latches to the numeric and byte modes (or
+/// back to text) cost a whole codeword (2 values, the padding of a pending
+/// text value not included). Shifts for a single character cost 1 value
+/// (to the punctuation sub-mode or from lower to uppercase) and a byte shift
+/// costs a codeword.
+pub const MODE_COSTS: [[u8; 6]; 6] = [
+    // Upper Lower Mixed Punc Numeric Byte
+    [0, 1, 1, 2, 2, 2], // Upper: ll, ml, ml+pl
+    [2, 0, 1, 2, 2, 2], // Lower: ps+al, ml, ml+pl
+    [1, 1, 0, 1, 2, 2], // Mixed: al, ll, pl
+    [1, 2, 2, 0, 2, 2], // Punc: al, al+ll, al+ml
+    [2, 3, 3, 4, 0, 2], // Numeric: text latch (to Upper) + latches
+    [2, 3, 3, 4, 2, 0], // Byte: text latch (to Upper) + latches
+];
+
+/// Returns the cost in text values of the latch from `from` to `to`, see
+/// [MODE_COSTS].
+pub const fn transition_cost(from: Mode, to: Mode) -> u8 {
+    MODE_COSTS[from as usize][to as usize]
+}
+
+/// Tag preceding the timestamps appended by [PDF417Encoder::append_timestamp].
+pub const TIMESTAMP_TAG: &str = "TS:";
+
+/// Number of bytes filling a whole symbol using the byte compaction.
+const MAX_BYTES: usize = MAX_SYMBOL_CODEWORDS / 5 * 6;
+
 const MIXED_CHAR_SET: [u8; 15] = [
     b'&', b'\r', b'\t', b',', b':', b'#', b'-', b'.', b'$', b'/', b'+', b'%', b'*', b'=', b'^'
 ];
@@ -63,7 +149,7 @@ macro_rules! push {
             $rh = false;
             $i += 1;
         } else {
-            $cws[$i] = cw;
+            *$cws.get_mut($i)? = cw;
             $rh = true;
         }
     }};
@@ -78,12 +164,449 @@ macro_rules! push_sp {
             $i += 1;
         }
 
-        $cws[$i] = cw;
+        *$cws.get_mut($i)? = cw;
         $i += 1;
         $post = $new;
     }};
 }
 
+/// Returns true if [PDF417Encoder::append_ascii] encodes the byte `c` using
+/// the text compaction (letters, digits, space and the characters of the
+/// mixed and punctuation tables). Other bytes are encoded with a byte shift
+/// which costs 2 codewords each.
+pub const fn is_text_encodable(c: u8) -> bool {
+    if c.is_ascii_alphanumeric() || c == b' ' {
+        return true;
+    }
+    let mut i = 0;
+    while i < PUNC_CHAR_SET.len() {
+        if PUNC_CHAR_SET[i] == c {
+            return true;
+        }
+        i += 1;
+    }
+    let mut i = 0;
+    while i < MIXED_CHAR_SET.len() {
+        if MIXED_CHAR_SET[i] == c {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Returns the exact number of codewords emitted by
+/// [PDF417Encoder::append_bytes] for a segment of `len` bytes, including the
+/// byte latch (or shift) codeword.
+pub const fn byte_segment_codewords(len: usize) -> usize {
+    if len > 1 {
+        // latch + 5 codewords per group of 6 bytes + 1 codeword per remaining byte
+        1 + (len / 6) * 5 + len % 6
+    } else {
+        // shift/latch + single byte
+        1 + len
+    }
+}
+
+/// Encodes the `len` bytes yielded by `bytes` using the byte compaction
+/// (groups of 6 bytes packed in 5 codewords, then one codeword per remaining
+/// byte) into `out`. The bytes are streamed, no buffer is needed to build
+/// them. Returns the number of codewords written.
+fn compact_bytes(out: &mut [u16], len: usize, mut bytes: impl Iterator<Item = u8>) -> usize {
+    let mut i = 0;
+    for _ in 0..len / 6 {
+        // pack six bytes
+        let mut s: u64 = 0;
+        for b in bytes.by_ref().take(6) {
+            s = (s << 8) + b as u64;
+        }
+        // append five codewords
+        for n in 0..5 {
+            let (q, r) = (s / BASE as u64, s % BASE as u64);
+            out[i + 4 - n] = r as u16;
+            s = q;
+        }
+        i += 5;
+    }
+
+    // remaining
+    for b in bytes {
+        out[i] = b as u16;
+        i += 1;
+    }
+    debug_assert_eq!(i, len / 6 * 5 + len % 6, "the iterator did not yield {len} bytes");
+    i
+}
+
+/// Text sub-mode in effect after the text `values` starting in the sub-mode
+/// `mode`, the shifts only apply to the value which follows them.
+fn submode_after(mut mode: Mode, values: &[u8]) -> Mode {
+    let mut values = values.iter();
+    while let Some(&v) = values.next() {
+        mode = match (mode, v) {
+            (Mode::Upper | Mode::Mixed, 27) => Mode::Lower,
+            (Mode::Upper | Mode::Lower, 28) => Mode::Mixed,
+            (Mode::Mixed, 25) => Mode::Punctuation,
+            (Mode::Mixed, 28) | (Mode::Punctuation, 29) => Mode::Upper,
+            (Mode::Upper | Mode::Lower | Mode::Mixed, 29) | (Mode::Lower, 27) => {
+                values.next(); // shifted value
+                mode
+            },
+            _ => mode,
+        };
+    }
+    mode
+}
+
+/// ASCII text fed to the text compaction, either a string or the fields of a
+/// record joined by a separator (see [PDF417Encoder::append_record]).
+trait TextSource {
+    fn len(&self) -> usize;
+
+    /// Character at the index `k` which must be less than the length.
+    fn at(&self, k: usize) -> u8;
+
+    fn get(&self, k: usize) -> Option<u8> {
+        if k < self.len() { Some(self.at(k)) } else { None }
+    }
+}
+
+impl TextSource for [u8] {
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    fn at(&self, k: usize) -> u8 {
+        self[k]
+    }
+}
+
+/// Fields of a record joined by a separator without copying them.
+struct Record<'r> {
+    fields: &'r [&'r str],
+    sep: u8,
+}
+
+impl TextSource for Record<'_> {
+    fn len(&self) -> usize {
+        self.fields.iter().map(|f| f.len()).sum::<usize>() + self.fields.len().saturating_sub(1)
+    }
+
+    fn at(&self, mut k: usize) -> u8 {
+        for field in self.fields {
+            match k.checked_sub(field.len()) {
+                None => return field.as_bytes()[k],
+                Some(0) => return self.sep,
+                Some(rest) => k = rest - 1,
+            }
+        }
+        unreachable!("index out of the record")
+    }
+}
+
+/// Encodes up to 44 ASCII `digits` using the numeric compaction (base 900
+/// conversion of the number prefixed by a leading 1) into `out`. Returns the
+/// number of codewords written.
+fn compact_numeric(out: &mut [u16], digits: &[u8]) -> usize {
+    debug_assert!(!digits.is_empty() && digits.len() <= 44, "numeric groups are made of 1 to 44 digits");
+    let count_digits = digits.len();
+
+    let mut b = U160::zero();
+    {
+        let mut p0 = U160::zero();
+        let mut p1 = U160::zero();
+        b.bytes_radix_(None, digits, 10, &mut p0, &mut p1)
+            .expect("45 digits base 10 should fit in 160 bits");
+
+        // Append a leading 1 to the number to do the base 900
+        // conversion. We need to calculate and add 10^(digits).
+        // Power of 10 (see https://stackoverflow.com/a/44103598)
+        p1.uone_();
+        p1.shl_(count_digits).unwrap();
+        for _ in 0..count_digits {
+            p0.copy_(&p1).unwrap();
+            p0.shl_(2).unwrap();
+            p1.add_(&p0).unwrap();
+        }
+        b.add_(&p1).unwrap();
+    }
+    let nb = count_digits / 3 + 1;
+    let mut count = 0;
+
+    while !b.is_zero() {
+        debug_assert!(count < nb, "numeric group of {count_digits} digits overflows its {nb} codewords");
+        let r = b.digit_udivide_inplace_(BASE as usize).expect("BASE > 0");
+        out[nb - count - 1] = r as u16;
+        count += 1;
+    }
+    // the leading 1 guarantees that exactly `nb` codewords are produced
+    debug_assert_eq!(count, nb, "numeric group of {count_digits} digits underflows its {nb} codewords");
+
+    nb
+}
+
+/// Error returned by [PDF417Encoder::try_append_raw] when a codeword is out
+/// of range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodewordError {
+    /// Index of the invalid codeword in the appended slice.
+    pub index: usize,
+    /// Invalid codeword (929 or more).
+    pub codeword: u16,
+}
+
+impl fmt::Display for CodewordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid codeword {} at index {} (must be less than {GF_MODULUS})", self.codeword, self.index)
+    }
+}
+
+/// Error returned by [PDF417Encoder::try_append_cp437] when a character can
+/// not be represented in the code page 437.
+#[cfg(feature = "cp437")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cp437Error {
+    /// Index of the unmappable character (in characters, not bytes).
+    pub index: usize,
+    /// Unmappable character.
+    pub character: char,
+}
+
+#[cfg(feature = "cp437")]
+impl fmt::Display for Cp437Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the character {:?} at index {} is not part of the code page 437", self.character, self.index)
+    }
+}
+
+/// Error returned by [PDF417Encoder::append_gtin].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GtinError {
+    /// The number of digits is not 8, 12, 13 or 14.
+    InvalidLength(usize),
+    /// The character at the index is not an ASCII digit.
+    InvalidDigit(usize),
+    /// The check digit (last digit) does not match the computed one.
+    CheckDigit { expected: u8, actual: u8 },
+}
+
+impl fmt::Display for GtinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            GtinError::InvalidLength(len) =>
+                write!(f, "invalid GTIN length {len} (must be 8, 12, 13 or 14 digits)"),
+            GtinError::InvalidDigit(index) =>
+                write!(f, "the GTIN character at index {index} is not a digit"),
+            GtinError::CheckDigit { expected, actual } =>
+                write!(f, "invalid GTIN check digit {actual} (expected {expected})"),
+        }
+    }
+}
+
+/// Error returned by [PDF417Encoder::try_seal].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SealError {
+    /// The ECC level is not between 0 and 8.
+    InvalidLevel(u8),
+    /// The MicroPDF417 variant is not between 0 and 33.
+    InvalidVariant(u8),
+    /// The ECC codewords (plus the length codeword for regular PDF417) do not
+    /// fit in the storage.
+    EccOverflow { ecc: usize, capacity: usize },
+    /// The data codewords overlap the ECC codewords of the ECC level.
+    DataOverflow { data: usize, capacity: usize, level: u8 },
+    /// The data codewords overlap the ECC codewords of the MicroPDF417
+    /// variant.
+    MicroDataOverflow { data: usize, capacity: usize, variant: u8 },
+}
+
+impl fmt::Display for SealError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            SealError::InvalidLevel(level) =>
+                write!(f, "invalid ECC level {level} (must be between 0 and 8)"),
+            SealError::InvalidVariant(variant) =>
+                write!(f, "invalid variant {variant} (must be between 0 and 33)"),
+            SealError::EccOverflow { ecc, capacity } =>
+                write!(f, "{ecc} ECC codewords do not fit in a storage of {capacity} codewords"),
+            SealError::DataOverflow { data, capacity, level } =>
+                write!(f, "data ({data}) exceeds capacity ({capacity}) for ECC level {level}"),
+            SealError::MicroDataOverflow { data, capacity, variant } =>
+                write!(f, "data ({data}) exceeds capacity ({capacity}) for variant {variant}"),
+        }
+    }
+}
+
+/// Returns the dimensions (rows, cols) of the smallest regular PDF417 able
+/// to hold `count` codewords (data, length and ECC codewords). When several
+/// dimensions have the same area, the one with the least rows is selected.
+/// None is returned if `count` exceeds the maximum size of a symbol.
+pub const fn best_dimensions(count: usize) -> Option<(u8, u8)> {
+    let mut best: Option<(u8, u8)> = None;
+    let mut best_area = usize::MAX;
+    let mut cols = 30;
+    while cols >= 1 {
+        // smallest number of rows (3-90) holding count codewords
+        let rows = (count + cols as usize - 1) / cols as usize;
+        let rows = if rows < 3 { 3 } else { rows };
+        let area = rows * cols as usize;
+        if rows <= 90 && area <= MAX_SYMBOL_CODEWORDS && area < best_area {
+            best = Some((rows as u8, cols));
+            best_area = area;
+        }
+        cols -= 1;
+    }
+    best
+}
+
+/// Returns the highest ECC level whose ECC codewords fit alongside
+/// `data_codewords` (including the length codeword) in a regular PDF417 of
+/// `rows` x `cols`. None is returned if the dimensions are invalid or if not
+/// even the level 0 fits.
+pub const fn max_level_for_dimensions(data_codewords: usize, rows: u8, cols: u8) -> Option<u8> {
+    if RegularVariant::try_new(rows, cols, 0).is_err() {
+        return None;
+    }
+    let capacity = rows as usize * cols as usize;
+
+    let mut level = 9;
+    while level > 0 && data_codewords + ecc::ecc_count(level - 1) > capacity {
+        level -= 1;
+    }
+    if level == 0 {
+        None
+    } else {
+        Some(level - 1)
+    }
+}
+
+/// Writes the length codeword (the number of data codewords including itself,
+/// i.e. rows \* cols - ECC codewords) of a regular PDF417 of `rows` x `cols`
+/// and ECC level `level` to `storage[0]`, as done by [PDF417Encoder::seal].
+/// Useful for codewords built by hand. Panics if the length of `storage` is
+/// not rows \* cols or if the ECC codewords do not fit.
+pub fn set_length_codeword(storage: &mut [u16], rows: u8, cols: u8, level: u8) {
+    let capacity = rows as usize * cols as usize;
+    assert_eq!(storage.len(), capacity, "The storage length must be equal to rows * cols");
+    assert!(level < 9, "ECC level must be between 0 and 8");
+    let ecc = ecc::ecc_count(level);
+    assert!(ecc < capacity, "{ecc} ECC codewords do not fit in a symbol of {capacity} codewords");
+    storage[0] = (capacity - ecc) as u16;
+}
+
+/// Encodes the ASCII string `s` (see [PDF417Encoder::append_ascii]) into an
+/// array of `N` codewords sealed with the ECC level `level`, ready to be
+/// rendered as a regular PDF417 of rows \* cols = `N` codewords. `N` must
+/// thus leave room for the ECC codewords of the level. Returns None if the
+/// data and the ECC codewords do not fit in `N` codewords.
+///
+/// ```
+/// # use pdf417::*;
+/// let codewords = encode_ascii::<{ 5 * 3 }>("Hello", 2).unwrap();
+/// let pdf417 = PDF417::new(&codewords, 5, 3, 2);
+/// ```
+pub fn encode_ascii<const N: usize>(s: &str, level: u8) -> Option<[u16; N]> {
+    if N == 0 {
+        return None;
+    }
+    let mut codewords = [0u16; N];
+    PDF417Encoder::new(&mut codewords, false)
+        .try_append_text(s.as_bytes())?
+        .try_seal(level).ok()?;
+    Some(codewords)
+}
+
+/// Computes the CRC-16/CCITT-FALSE of `data` (polynomial 0x1021, initial
+/// value 0xFFFF, no reflection and no final XOR) as appended by
+/// [PDF417Encoder::append_with_crc16].
+pub const fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    let mut i = 0;
+    while i < data.len() {
+        crc ^= (data[i] as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+            bit += 1;
+        }
+        i += 1;
+    }
+    crc
+}
+
+/// Returns the ECC level recommended by the specification for `count` data
+/// codewords.
+#[cfg(feature = "alloc")]
+const fn recommended_level(count: usize) -> u8 {
+    match count {
+        0..=40 => 2,
+        41..=160 => 3,
+        161..=320 => 4,
+        _ => 5,
+    }
+}
+
+/// Returns the byte representing `c` in the code page 437 (see
+/// [PDF417Encoder::append_cp437]), None if it is not part of the code page.
+#[cfg(feature = "cp437")]
+pub fn cp437_byte(c: char) -> Option<u8> {
+    if c.is_ascii() {
+        Some(c as u8)
+    } else {
+        crate::tables::CP437_HIGH.iter().position(|&r| r == c).map(|p| 0x80 + p as u8)
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Unsigned primitive integer types accepted by
+/// [PDF417Encoder::append_int]. Signed integers must be converted first
+/// (e.g. with `u64::try_from`) as negative numbers can not be encoded. This
+/// trait is sealed and can not be implemented outside of this crate.
+pub trait NumericInt: private::Sealed + Copy {
+    /// Converts the integer to an u128.
+    fn to_u128(self) -> u128;
+}
+
+macro_rules! numeric_int {
+    ($($t:ty),*) => {$(
+        impl private::Sealed for $t {}
+        impl NumericInt for $t {
+            #[inline]
+            fn to_u128(self) -> u128 {
+                self as u128
+            }
+        }
+    )*};
+}
+
+numeric_int!(u8, u16, u32, u64, u128, usize);
+
+/// Logical state of a [PDF417Encoder], see [PDF417Encoder::snapshot].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderState {
+    used: usize,
+    last_mode: Mode,
+    padded: usize,
+}
+
+/// Tunable parameters of the [PDF417Encoder] heuristics.
+#[derive(Debug, Clone, Copy)]
+struct Options {
+    /// Minimum number of consecutive punctuation characters to latch to the
+    /// punctuation mode instead of shifting.
+    punc_threshold: u8,
+    /// Maximum number of consecutive digits encoded in the mixed mode,
+    /// longer runs use the numeric compaction.
+    numeric_threshold: u8,
+}
+
+impl Options {
+    const DEFAULT: Options = Options { punc_threshold: 3, numeric_threshold: 13 };
+}
+
 /// Use a PDF417Encoder to encode your data segements to a slice of codewords
 /// ready to be rendered.
 #[derive(Debug)]
@@ -92,7 +615,13 @@ pub struct PDF417Encoder<'a> {
     used: usize,
     micro: bool,
     // 0: Upper, 1: Lower, 2: Mixed, 3: Punc, 4: Numeric, 5: Byte
-    last_mode: u8
+    last_mode: Mode,
+    /// Value of `used` right after a text segment ending with a padding
+    /// value (0 if the last segment was not padded).
+    padded: usize,
+    /// The length codeword slot is not reserved, see [PDF417Encoder::new_raw].
+    raw: bool,
+    options: Options,
 }
 
 impl<'a> PDF417Encoder<'a> {
@@ -102,54 +631,213 @@ impl<'a> PDF417Encoder<'a> {
     /// data according to the classic PDF417, you can not use it to generate
     /// a MicroPDF417 and vice-versa.
     pub fn new(storage: &'a mut [u16], micro: bool) -> Self {
-        assert!(storage.len() > 0, "storage must be able to contain at least one codeword");
+        assert!(!storage.is_empty(), "storage must be able to contain at least one codeword");
         if micro {
             // Default mode is byte compactation
-            Self { storage, used: 0, micro, last_mode: 5 } 
+            Self { storage, used: 0, micro, last_mode: Mode::Byte, padded: 0, raw: false, options: Options::DEFAULT }
         } else {
             // Skip the first codeword (used for length).
-            Self { storage, used: 1, micro, last_mode: 0 } 
+            Self { storage, used: 1, micro, last_mode: Mode::Upper, padded: 0, raw: false, options: Options::DEFAULT }
         }
     }
 
+    /// Same as [PDF417Encoder::new] but the first codeword of a regular
+    /// PDF417 is not reserved for the length codeword, the data starts at
+    /// index 0, e.g. for tools managing the length themselves. **Note**:
+    /// [PDF417Encoder::seal] still pads the data and appends the ECC
+    /// codewords but does not write the length codeword, the first codeword
+    /// being data, the sealed symbol is only readable if the caller makes
+    /// it a valid length codeword.
+    pub fn new_raw(storage: &'a mut [u16], micro: bool) -> Self {
+        let encoder = Self::new(storage, micro);
+        Self { used: 0, raw: true, ..encoder }
+    }
+
     /// Returns the number of codewords already used
     pub fn count(&self) -> usize {
         self.used
     }
 
-    /// Returns the number of available codewords (excluding required ECC codewords).
+    /// Same as [PDF417Encoder::count].
+    pub fn len(&self) -> usize {
+        self.used
+    }
+
+    /// Returns true if no data has been appended yet (the length codeword
+    /// slot of regular PDF417 is not data).
+    pub fn is_empty(&self) -> bool {
+        self.used == self.header()
+    }
+
+    /// Returns true if the data is encoded according to the MicroPDF417
+    /// specification (see [PDF417Encoder::new]). The sealed codewords must be
+    /// rendered with [crate::MicroPDF417] if true, [crate::PDF417] otherwise.
+    pub fn is_micro(&self) -> bool {
+        self.micro
+    }
+
+    /// Sets the minimum number of consecutive punctuation characters required
+    /// by [PDF417Encoder::append_ascii] to latch to the punctuation mode
+    /// (defaults to 3). Shorter runs are encoded using punctuation shifts.
+    /// Any value produces a valid symbol, a value of 1 always latches.
+    pub fn set_punctuation_threshold(mut self, threshold: u8) -> Self {
+        self.options.punc_threshold = threshold;
+        self
+    }
+
+    /// Sets the maximum number of consecutive digits encoded by
+    /// [PDF417Encoder::append_ascii] in the mixed mode of the text compaction
+    /// (defaults to 13). Longer runs are encoded using the numeric
+    /// compaction. Any value produces a valid symbol, a value of 0 always
+    /// uses the numeric compaction.
+    pub fn set_numeric_threshold(mut self, threshold: u8) -> Self {
+        self.options.numeric_threshold = threshold;
+        self
+    }
+
+    /// Returns the codewords appended so far (including the length codeword
+    /// slot for regular PDF417).
+    pub fn as_codewords(&self) -> &[u16] {
+        &self.storage[..self.used]
+    }
+
+    /// Captures the logical state of the encoder so it can be rolled back
+    /// later with [PDF417Encoder::restore], e.g. to try different suffixes
+    /// after a common prefix.
+    pub fn snapshot(&self) -> EncoderState {
+        EncoderState { used: self.used, last_mode: self.last_mode, padded: self.padded }
+    }
+
+    /// Rolls back the encoder to a state captured with
+    /// [PDF417Encoder::snapshot]. The codewords appended before the snapshot
+    /// are kept, the ones appended after are discarded. The state must come
+    /// from an encoder working on the same storage.
+    pub fn restore(&mut self, state: EncoderState) {
+        assert!(state.used <= self.storage.len(), "the state does not belong to this encoder");
+        self.used = state.used;
+        self.last_mode = state.last_mode;
+        self.padded = state.padded;
+    }
+
+    /// Returns the total number of codewords of the storage, including the
+    /// slots of the length codeword and of the ECC codewords inserted when
+    /// sealing. See [PDF417Encoder::data_capacity] for the data only.
     pub fn capacity(&self) -> usize {
         self.storage.len()
     }
 
+    /// Returns the number of codewords left for the data (including the
+    /// length codeword of a regular PDF417) once the ECC codewords are
+    /// inserted, 0 if they do not fit. The `val` parameter is the same as
+    /// [PDF417Encoder::available].
+    pub fn data_capacity(&self, val: u8) -> usize {
+        self.storage.len().saturating_sub(self.ecc_count(val))
+    }
+
     /// Returns the number of free codeword slots including slots filled up by
     /// ECC codewords. Be careful, when generating a MicroPDF417 the `val`
     /// parameter represents the variant, otherwise the `val` parameter
-    /// represents the ECC level.
+    /// represents the ECC level. Panics if the data already exceeds the
+    /// capacity, prefer [PDF417Encoder::available_regular] and
+    /// [PDF417Encoder::available_micro].
     pub fn available(&self, val: u8) -> usize {
-        let ecc_count = if self.micro {
+        self.storage.len().checked_sub(self.ecc_count(val))
+            .and_then(|n| n.checked_sub(self.used))
+            .expect("the data and the ECC codewords exceed the capacity")
+    }
+
+    /// Returns the ratio of the data codeword slots (capacity minus the ECC
+    /// codewords and the length codeword slot) already used, from 0 (empty)
+    /// to 1 (full). The `val` parameter is the same as
+    /// [PDF417Encoder::available]. The ratio exceeds 1 if the data does not
+    /// fit alongside the ECC codewords. None is returned if there is no slot
+    /// left for the data once the ECC codewords are inserted.
+    pub fn utilization(&self, val: u8) -> Option<f32> {
+        let header = self.header();
+        let slots = self.data_capacity(val).checked_sub(header).filter(|&n| n > 0)?;
+        Some((self.used - header) as f32 / slots as f32)
+    }
+
+    /// Number of codewords reserved before the data (the length codeword
+    /// slot of a regular PDF417).
+    fn header(&self) -> usize {
+        if self.micro || self.raw { 0 } else { 1 }
+    }
+
+    /// Number of ECC codewords of the level (or variant) `val`.
+    fn ecc_count(&self, val: u8) -> usize {
+        if self.micro {
             let variant = val as usize;
-            assert!(variant <= 34, "invalid variant (0-34)");
             use crate::tables::*;
+            assert!(variant < M_PDF417_VARIANTS_COUNT, "invalid variant (0-33)");
             M_PDF417_VARIANTS[2 * M_PDF417_VARIANTS_COUNT + variant] as usize
         } else {
             let level = val;
             ecc::ecc_count(level)
-        };
-        self.storage.len() - ecc_count - self.used
+        }
     }
 
-    /// Appends a numeric segment containing a 64-bit unsigned integer `n`. For
-    /// larger numbers please use the [PDF417Encoder::append_ascii] method which
-    /// can handle 44+ digit numbers.
-    pub fn append_num(mut self, mut n: u64) -> Self {
-        if self.last_mode != 4 {
+    /// Returns the number of free codeword slots of a regular PDF417 once the
+    /// ECC codewords of the level `level` (0-8) are inserted. None is
+    /// returned if the data and the ECC codewords do not fit in the storage.
+    pub fn available_regular(&self, level: u8) -> Option<usize> {
+        assert!(!self.micro, "available_regular requires an encoder created with micro = false");
+        self.storage.len().checked_sub(ecc::ecc_count(level))?.checked_sub(self.used)
+    }
+
+    /// Returns the number of free codeword slots of the MicroPDF417 variant
+    /// `variant` (0-33) once its ECC codewords are inserted. None is returned
+    /// if the data and the ECC codewords do not fit in the variant.
+    pub fn available_micro(&self, variant: u8) -> Option<usize> {
+        assert!(self.micro, "available_micro requires an encoder created with micro = true");
+        assert!((variant as usize) < crate::tables::M_PDF417_VARIANTS_COUNT, "invalid variant (0-33)");
+        Variant::from(variant).data_capacity().checked_sub(self.used)
+    }
+
+    /// Appends a numeric segment containing a 64-bit unsigned integer `n`
+    /// (0 is encoded as the digit `0`). For larger numbers please use the
+    /// [PDF417Encoder::append_int] or the [PDF417Encoder::append_ascii]
+    /// method which can handle 44+ digit numbers.
+    pub fn append_num(self, n: u64) -> Self {
+        self.append_u128(n as u128)
+    }
+
+    /// Same as [PDF417Encoder::append_num] for any unsigned primitive integer
+    /// type (see [NumericInt]), up to [u128::MAX]. Signed integers are not
+    /// accepted:
+    ///
+    /// ```compile_fail
+    /// # use pdf417::*;
+    /// let mut storage = [0u16; 8];
+    /// PDF417Encoder::new(&mut storage, false).append_int(-1i32);
+    /// ```
+    pub fn append_int<T: NumericInt>(self, n: T) -> Self {
+        let n = n.to_u128();
+        if n < 10u128.pow(38) {
+            self.append_u128(n)
+        } else {
+            // the leading 1 of 39-digit numbers does not fit in a u128
+            let mut digits = [0u8; 39];
+            let (mut val, mut k) = (n, digits.len());
+            while val > 0 {
+                k -= 1;
+                digits[k] = b'0' + (val % 10) as u8;
+                val /= 10;
+            }
+            self.append_numeric_bytes(&digits[k..])
+        }
+    }
+
+    /// Appends a numeric segment for a number of at most 38 digits.
+    fn append_u128(mut self, n: u128) -> Self {
+        if self.last_mode != Mode::Numeric {
             self.storage[self.used] = M_LATCH_NUMERIC;
-            self.last_mode = 4;
+            self.last_mode = Mode::Numeric;
             self.used += 1;
         }
 
         let mut digits = 0;
+        let mut n = n;
 
         // Append a leading 1 to the number to do the base 900
         // conversion. We need to calculate and add 10^(digits).
@@ -158,204 +846,354 @@ impl<'a> PDF417Encoder<'a> {
             let mut val = n;
             let mut p1 = 1;
 
-            while val > 0 {
+            // 0 is encoded as a single digit
+            loop {
                 p1 += p1 << 2; // *5
                 val /= 10;
                 digits += 1;
+                if val == 0 {
+                    break;
+                }
             }
             p1 <<= digits;
             n += p1;
         }
 
         let nb = digits / 3 + 1;
+        debug_assert!(digits <= 38, "a number of {digits} digits does not fit in a u128 with its leading 1");
+        debug_assert!(self.used + nb <= self.storage.len(),
+            "the numeric segment of {nb} codewords does not fit in the storage ({} codewords left)",
+            self.storage.len() - self.used);
         let mut count = 0;
 
         while n > 0 {
-            let (q, r) = (n / 900, n % 900);
+            let (q, r) = (n / BASE as u128, n % BASE as u128);
             n = q;
             self.storage[self.used + nb - count - 1] = r as u16;
             count += 1;
         }
+        debug_assert_eq!(count, nb, "number of {digits} digits underflows its {nb} codewords");
 
         self.used += nb;
         self
     }
 
-    /// Appends a bytes segment.
-    pub fn append_bytes(mut self, bytes: &[u8]) -> Self {
-        let mut i = self.used;
-        let mut k = 0;
+    /// Appends a numeric segment for each number of `nums`. Consecutive
+    /// numeric segments would be read back as a single number, so the
+    /// numbers are separated by a space encoded in text mode (a text latch
+    /// and a codeword holding the space, then a numeric latch for the next
+    /// number). The numbers `[12, 34]` are thus read back as `"12 34"`.
+    pub fn append_nums<I: IntoIterator<Item = u64>>(mut self, nums: I) -> Self {
+        for (i, n) in nums.into_iter().enumerate() {
+            if i > 0 {
+                self = self.append_ascii(" ");
+            }
+            self = self.append_num(n);
+        }
+        self
+    }
 
-        if bytes.len() > 1 {
-            // even if we are in byte mode, it is safer to always emit a LATCH_BYTE
-            self.storage[i] = if bytes.len() % 6 == 0 { M_LATCH_BYTE_M6 } else { M_LATCH_BYTE };
-            self.last_mode = 5;
-            i += 1;
+    /// Appends a numeric segment made of ASCII digits (`b'0'..=b'9'`) using
+    /// the numeric compaction. Unlike [PDF417Encoder::append_num], leading
+    /// zeros are preserved and there is no limit on the number of digits.
+    /// Panics if `digits` contains a non-digit byte.
+    pub fn append_numeric_bytes(mut self, digits: &[u8]) -> Self {
+        assert!(digits.iter().all(u8::is_ascii_digit), "numeric segments can only contain ASCII digits");
+        if digits.is_empty() {
+            return self;
+        }
 
-            while bytes.len()-k >= 6 {
-                // pack six bytes
-                let mut s: u64 = 0;
-                for n in 0..6 {
-                    s = (s << 8) + bytes[k + n] as u64;
-                }
-                // append five codewords
-                for n in 0..5 {
-                    let (q, r) = (s / 900, s % 900);
-                    self.storage[i + 4 - n] = r as u16;
-                    s = q;
-                }
+        if self.last_mode != Mode::Numeric {
+            self.storage[self.used] = M_LATCH_NUMERIC;
+            self.last_mode = Mode::Numeric;
+            self.used += 1;
+        }
 
-                i += 5;
-                k += 6;
-            }
-        } else {
-            if self.last_mode < 4 { // if in text mode
-                self.storage[i] = M_SHIFT_BYTE;
-            } else {
-                self.storage[i] = M_LATCH_BYTE;
-                self.last_mode = 5;
-            }
-            i += 1;
+        for group in digits.chunks(44) {
+            self.used += compact_numeric(&mut self.storage[self.used..], group);
         }
+        self
+    }
 
-        // remaining
-        while k < bytes.len() {
-            self.storage[i] = bytes[k] as u16;
-            k += 1;
-            i += 1;
+    /// Appends a GTIN (GTIN-8, GTIN-12/UPC-A, GTIN-13/EAN-13 or GTIN-14)
+    /// given as its digits including the check digit using the numeric
+    /// compaction (see [PDF417Encoder::append_numeric_bytes], leading zeros
+    /// are preserved). Returns a [GtinError] if the length is invalid, if a
+    /// character is not a digit or if the GS1 mod-10 check digit is wrong.
+    pub fn append_gtin(self, digits: &str) -> Result<Self, GtinError> {
+        let digits = digits.as_bytes();
+        if !matches!(digits.len(), 8 | 12 | 13 | 14) {
+            return Err(GtinError::InvalidLength(digits.len()));
+        }
+        if let Some(index) = digits.iter().position(|c| !c.is_ascii_digit()) {
+            return Err(GtinError::InvalidDigit(index));
+        }
+
+        let (payload, check) = digits.split_at(digits.len() - 1);
+        // weights alternate 3, 1, 3... from the rightmost payload digit
+        let sum: u32 = payload.iter().rev().enumerate()
+            .map(|(i, &c)| (c - b'0') as u32 * if i % 2 == 0 { 3 } else { 1 })
+            .sum();
+        let expected = ((10 - sum % 10) % 10) as u8;
+        let actual = check[0] - b'0';
+        if expected != actual {
+            return Err(GtinError::CheckDigit { expected, actual });
+        }
+
+        Ok(self.append_numeric_bytes(digits))
+    }
+
+    /// Appends a bytes segment.
+    pub fn append_bytes(self, bytes: &[u8]) -> Self {
+        self.append_byte_iter(bytes.len(), bytes.iter().copied())
+    }
+
+    /// Same as [PDF417Encoder::append_bytes] for the `len` bytes yielded by
+    /// `bytes`, which are compacted as they come instead of being collected
+    /// in a buffer first.
+    fn append_byte_iter(mut self, len: usize, bytes: impl Iterator<Item = u8>) -> Self {
+        let mut i = self.used;
+
+        if len > 1 {
+            // even if we are in byte mode, it is safer to always emit a LATCH_BYTE
+            self.storage[i] = if len % 6 == 0 { M_LATCH_BYTE_M6 } else { M_LATCH_BYTE };
+            self.last_mode = Mode::Byte;
+        } else if self.last_mode.is_text() {
+            self.storage[i] = M_SHIFT_BYTE;
+        } else {
+            self.storage[i] = M_LATCH_BYTE;
+            self.last_mode = Mode::Byte;
         }
+        i += 1;
+        i += compact_bytes(&mut self.storage[i..], len, bytes);
 
         self.used = i;
         self
     }
 
+    /// Appends a bytes segment preceded by its length in bytes encoded as a
+    /// numeric segment (see [PDF417Encoder::append_num]). With
+    /// [PDF417Encoder::append_bytes], the length is implied by the number of
+    /// codewords and the latch: [M_LATCH_BYTE_M6] when it is a multiple of 6,
+    /// otherwise [M_LATCH_BYTE] with the last 1 to 5 bytes stored one per
+    /// codeword. A trailing group of 5 single bytes then looks like 5 packed
+    /// codewords to a reader ignoring the latch. As the specification has no
+    /// byte count field, the explicit count is an application convention:
+    /// the reader gets the length as a number before the bytes.
+    pub fn append_bytes_with_count(self, bytes: &[u8]) -> Self {
+        self.append_num(bytes.len() as u64).append_bytes(bytes)
+    }
+
+    /// Appends `data` followed by its CRC-16 (see [crc16_ccitt]) as 2
+    /// big-endian bytes, all in a single byte segment, so that the payload
+    /// can be verified once decoded independently of the ECC codewords.
+    /// Panics if `data` can not fit in a symbol.
+    pub fn append_with_crc16(self, data: &[u8]) -> Self {
+        assert!(data.len() + 2 <= MAX_BYTES, "too many bytes to fit in a symbol");
+
+        let crc = crc16_ccitt(data).to_be_bytes();
+        self.append_byte_iter(data.len() + 2, data.iter().copied().chain(crc))
+    }
+
+    /// Appends `data` preceded by its length as 2 big-endian bytes, all in
+    /// a single byte segment (see [PDF417Encoder::append_bytes]), e.g. for
+    /// a TLV based protocol. Panics if the length does not fit in 16 bits or
+    /// if `data` can not fit in a symbol.
+    pub fn append_length_prefixed(self, data: &[u8]) -> Self {
+        let len = u16::try_from(data.len()).expect("the length of the data must fit in 16 bits");
+        assert!(data.len() + 2 <= MAX_BYTES, "too many bytes to fit in a symbol");
+
+        self.append_byte_iter(data.len() + 2, len.to_be_bytes().into_iter().chain(data.iter().copied()))
+    }
+
+    /// Appends a bytes segment made of the booleans `bits` packed MSB first
+    /// (the first boolean is the most significant bit of the first byte).
+    /// `ceil(bits.len() / 8)` bytes are appended, if the number of booleans
+    /// is not a multiple of 8, the last byte is padded with 0 bits (least
+    /// significant bits). The number of booleans is not encoded, the reader
+    /// must know it to ignore the padding. 12 booleans produce 2 bytes, the
+    /// 4 last bits of the second byte being padding.
+    pub fn append_bits(self, bits: &[bool]) -> Self {
+        // enough bytes to fill a whole symbol using the byte compaction
+        assert!(bits.len() <= MAX_BYTES * 8, "too many bits to fit in a symbol");
+
+        let bytes = bits.chunks(8).map(|byte| {
+            byte.iter().enumerate().fold(0u8, |b, (i, &bit)| b | ((bit as u8) << (7 - i)))
+        });
+        self.append_byte_iter((bits.len() + 7) / 8, bytes)
+    }
+
     /// Appends an ASCII (text) segment. *Warning*: This function uses the
     /// PDF417 table based encoding to optimize the size of the text and
     /// therefore support only a small set of displayable characters. If you
     /// want to encode an UTF-8 string, use [PDF417Encoder::append_utf8] instead
     /// (uses more space).
-    pub fn append_ascii(mut self, s: &str) -> Self {
-        debug_assert!(s.is_ascii(), "use append_utf8 for UTF-8 strings");
+    pub fn append_ascii(self, s: &str) -> Self {
+        self.append_text(s.as_bytes())
+    }
+
+    /// Appends a timestamp given in seconds since the Unix epoch as the text
+    /// [TIMESTAMP_TAG] (`TS:`) followed by the seconds in decimal using the
+    /// numeric compaction, e.g. `TS:1700000000` once decoded, so that a
+    /// reader can locate the field by its tag.
+    pub fn append_timestamp(self, unix_seconds: u64) -> Self {
+        self.append_ascii(TIMESTAMP_TAG).append_num(unix_seconds)
+    }
+
+    /// Appends a GPS coordinate given in microdegrees (degrees \* 10^6) as a
+    /// text segment of fixed layout `±LLLLLLLL,±GGGGGGGGG`: the latitude
+    /// zero-padded to 8 digits and the longitude to 9 digits, both always
+    /// signed. Panics if the latitude is not within ±90° or the longitude
+    /// within ±180°.
+    pub fn append_coordinate(self, lat_micro: i32, lon_micro: i32) -> Self {
+        assert!(lat_micro.unsigned_abs() <= 90_000_000, "the latitude must be between -90 and 90 degrees");
+        assert!(lon_micro.unsigned_abs() <= 180_000_000, "the longitude must be between -180 and 180 degrees");
+
+        let mut buf = *b"+00000000,+000000000";
+        let (lat, lon) = buf.split_at_mut(9);
+        for (field, value) in [(lat, lat_micro), (&mut lon[1..], lon_micro)] {
+            if value < 0 {
+                field[0] = b'-';
+            }
+            let mut n = value.unsigned_abs();
+            for digit in field[1..].iter_mut().rev() {
+                *digit = b'0' + (n % 10) as u8;
+                n /= 10;
+            }
+        }
+        let s = core::str::from_utf8(&buf).expect("the coordinate is ASCII");
+        self.append_ascii(s)
+    }
+
+    /// Appends an ASCII (text) segment made of the fields `fields` separated
+    /// by `sep`, e.g. a CSV record, without copying them. The fields are
+    /// encoded as a single text, the output is thus the same as
+    /// [PDF417Encoder::append_ascii] with the joined fields. Panics if `sep`
+    /// is not encodable in text mode (see [is_text_encodable]).
+    pub fn append_record(self, fields: &[&str], sep: char) -> Self {
+        assert!(sep.is_ascii() && is_text_encodable(sep as u8), "the separator must be encodable in text mode");
+        self.append_text(&Record { fields, sep: sep as u8 })
+    }
+
+    /// Same as [PDF417Encoder::append_ascii] for any [TextSource].
+    fn append_text<S: TextSource + ?Sized>(self, s: &S) -> Self {
+        self.try_append_text(s).expect("the text does not fit in the storage")
+    }
+
+    /// Same as [PDF417Encoder::append_text] but returns None if the text does
+    /// not fit in the storage. A codeword is only written if it is part of
+    /// the encoded text, so the first out of bounds write means the text
+    /// does not fit.
+    fn try_append_text<S: TextSource + ?Sized>(mut self, s: &S) -> Option<Self> {
+        debug_assert!((0..s.len()).all(|k| s.at(k).is_ascii()), "use append_utf8 for UTF-8 strings");
         let out = &mut self.storage;
-        let s = s.as_bytes();
 
-        let mut mode: u8 = self.last_mode; // 0: Upper, 1: Lower, 2: Mixed, 3: Punc, 4: Numeric, 5: Byte
+        let mut mode = self.last_mode;
         let mut i = self.used;
         let mut k = 0;
         let mut right = false; // false = upper 8 bits | true = lower 8 bits
 
-        if mode == 4 || mode == 5 {
-            out[i] = M_LATCH_TEXT;
+        if !mode.is_text() {
+            *out.get_mut(i)? = M_LATCH_TEXT;
             i += 1;
-            mode = 0;
+            mode = Mode::Upper;
+        } else if self.padded == i && i > 0 {
+            // the previous text segment ended with a padding value (a shift
+            // to punctuation) which would alter the first character of this
+            // segment: continue the text in place of the padding instead
+            i -= 1;
+            out[i] /= 30;
+            right = true;
         }
 
         while k < s.len() {
-            let c = s[k];
+            let c = s.at(k);
             match c {
                 c if c.is_ascii_uppercase() => { // b'A'..=b'Z'
                     match mode {
-                        0 => (),
-                        1 => if k + 1 < s.len() && s[k + 1].is_ascii_lowercase() {
+                        Mode::Upper => (),
+                        Mode::Lower => if s.get(k + 1).is_some_and(|n| n.is_ascii_lowercase()) {
                             push!(out, i, right, 27);
                         } else {
-                            push!(out, i, right, 29, 29; mode = 0);
+                            push!(out, i, right, 29, 29; mode = Mode::Upper);
                         },
-                        2 => push!(out, i, right, 28; mode = 0),
-                        3 => push!(out, i, right, 29; mode = 0),
-                        _ => unreachable!("Unknown mode {mode}"),
+                        Mode::Mixed => push!(out, i, right, 28; mode = Mode::Upper),
+                        Mode::Punctuation => push!(out, i, right, 29; mode = Mode::Upper),
+                        _ => unreachable!("Unknown mode {mode:?}"),
                     }
                     push!(out, i, right, c - b'A'; k = k + 1);
                 },
                 c if c.is_ascii_lowercase() => { // b'a'..=b'z'
                     match mode {
-                        0 | 2 => push!(out, i, right, 27; mode = 1),
-                        1 => (),
-                        3 => push!(out, i, right, 29, 27; mode = 1),
-                        _ => unreachable!("Unknown mode {mode}"),
+                        Mode::Upper | Mode::Mixed => push!(out, i, right, 27; mode = Mode::Lower),
+                        Mode::Lower => (),
+                        Mode::Punctuation => push!(out, i, right, 29, 27; mode = Mode::Lower),
+                        _ => unreachable!("Unknown mode {mode:?}"),
                     }
                     push!(out, i, right, c - b'a'; k = k + 1);
                 },
                 c if c.is_ascii_digit() => { // b'0'..=b'9'
                     let mut end = k + 1;
-                    while end < s.len() && end-k < 44 && s[end].is_ascii_digit() {
+                    while end < s.len() && end-k < 44 && s.at(end).is_ascii_digit() {
                         end += 1;
                     }
                     let digits = end - k;
 
-                    if digits <= 13 && mode != 4 {
+                    if digits <= self.options.numeric_threshold as usize && mode != Mode::Numeric {
                         match mode {
-                            0 | 1 => push!(out, i, right, 28; mode = 2),
-                            2 => (),
-                            3 => push!(out, i, right, 29, 28; mode = 2),
-                            _ => unreachable!("Unknown mode {mode}"),
+                            Mode::Upper | Mode::Lower => push!(out, i, right, 28; mode = Mode::Mixed),
+                            Mode::Mixed => (),
+                            Mode::Punctuation => push!(out, i, right, 29, 28; mode = Mode::Mixed),
+                            _ => unreachable!("Unknown mode {mode:?}"),
                         }
                         while k < end {
-                            push!(out, i, right, s[k] - b'0'; k = k + 1);
+                            push!(out, i, right, s.at(k) - b'0'; k = k + 1);
                         }
                     } else {
-                        if mode != 4 { push_sp!(out, i, right, M_LATCH_NUMERIC; mode = 4); }
-
-                        let mut b = U160::zero();
-                        {
-                            let mut p0 = U160::zero();
-                            let mut p1 = U160::zero();
-                            b.bytes_radix_(None, &s[k..end], 10, &mut p0, &mut p1)
-                                .expect("45 digits base 10 should fit in 160 bits");
-
-                            // Append a leading 1 to the number to do the base 900
-                            // conversion. We need to calculate and add 10^(digits).
-                            // Power of 10 (see https://stackoverflow.com/a/44103598)
-                            p1.uone_();
-                            p1.shl_(digits).unwrap();
-                            for _ in 0..digits {
-                                p0.copy_(&p1).unwrap();
-                                p0.shl_(2).unwrap();
-                                p1.add_(&p0).unwrap();
-                            }
-                            b.add_(&p1).unwrap();
-                        }
-                        let nb = digits / 3 + 1;
-                        let mut count = 0;
+                        if mode != Mode::Numeric { push_sp!(out, i, right, M_LATCH_NUMERIC; mode = Mode::Numeric); }
 
-                        while !b.is_zero() {
-                            let r = b.digit_udivide_inplace_(900).expect("900 > 0");
-                            out[i + nb - count - 1] = r as u16;
-                            count += 1;
+                        let mut group = [0u8; 44];
+                        for (d, j) in group.iter_mut().zip(k..end) {
+                            *d = s.at(j);
                         }
-
+                        i += compact_numeric(out.get_mut(i..i + digits / 3 + 1)?, &group[..digits]);
                         k = end;
-                        i += nb;
                     }
 
-                    if mode == 4 && k < s.len() && !s[k].is_ascii_digit() {
-                        push_sp!(out, i, right, M_LATCH_TEXT; mode = 0);
+                    if mode == Mode::Numeric && s.get(k).is_some_and(|n| !n.is_ascii_digit()) {
+                        push_sp!(out, i, right, M_LATCH_TEXT; mode = Mode::Upper);
                     }
                 },
                 b' ' => {
-                    if mode == 3 { push!(out, i, right, 29; mode = 0) };
+                    if mode == Mode::Punctuation { push!(out, i, right, 29; mode = Mode::Upper) };
                     push!(out, i, right, 26; k = k + 1);
                 },
                 c => {
-                    if let Some(p) = MIXED_CHAR_SET.iter().position(|&r| r == c) {
+                    // also present in the punc table
+                    let in_punc = |p: usize| (1..=4).contains(&p) || (6..=9).contains(&p);
+                    // a punc shift is cheaper than latching to mixed and back
+                    let isolated = s.get(k + 1).map_or(true, |n| !n.is_ascii_digit() && !MIXED_CHAR_SET.contains(&n));
+
+                    if let Some(p) = MIXED_CHAR_SET.iter().position(|&r| r == c).filter(|&p| !(matches!(mode, Mode::Upper | Mode::Lower) && isolated && in_punc(p))) {
                         match mode {
-                            0 | 1 => push!(out, i, right, 28; mode = 2),
-                            2 => (),
+                            Mode::Upper | Mode::Lower => push!(out, i, right, 28; mode = Mode::Mixed),
+                            Mode::Mixed => (),
                             /* no switch if the char is also present in the punc table */
-                            3 if (1..=4).contains(&p) || (6..=9).contains(&p) => (),
-                            3 => push!(out, i, right, 29, 28; mode = 2),
-                            _ => unreachable!("Unknown mode {mode}"),
+                            Mode::Punctuation if in_punc(p) => (),
+                            Mode::Punctuation => push!(out, i, right, 29, 28; mode = Mode::Mixed),
+                            _ => unreachable!("Unknown mode {mode:?}"),
                         }
                         push!(out, i, right, p + 10);
                     } else if let Some(p) = PUNC_CHAR_SET.iter().position(|&r| r == c) {
-                        if mode != 3 {
+                        if mode != Mode::Punctuation {
                             let mut end = k + 1;
-                            while end < s.len() && end-k < 3 && PUNC_CHAR_SET.contains(&s[end]) {
+                            let threshold = self.options.punc_threshold as usize;
+                            while end < s.len() && end-k < threshold && PUNC_CHAR_SET.contains(&s.at(end)) {
                                 end += 1;
                             }
-                            if end-k >= 3 { // latch
-                                if mode != 2 { push!(out, i, right, 28); }
-                                push!(out, i, right, 25; mode = 3);
+                            if end-k >= threshold { // latch
+                                if mode != Mode::Mixed { push!(out, i, right, 28); }
+                                push!(out, i, right, 25; mode = Mode::Punctuation);
                             } else { // shift
                                 push!(out, i, right, 29);
                             }
@@ -363,12 +1201,14 @@ impl<'a> PDF417Encoder<'a> {
                         push!(out, i, right, p);
                     } else { // switch to byte mode
                         if right {
+                            // the padding shift is consumed by the byte shift
                             out[i] = out[i] * 30 + 29;
+                            right = false;
                             i += 1;
                         }
                         // TODO: Encode multiple bytes if consecutive instead of one by one
+                        *out.get_mut(i + 1)? = c as u16;
                         out[i] = M_SHIFT_BYTE;
-                        out[i + 1] = c as u16;
                         i += 2;
                     }
                     k += 1;
@@ -376,61 +1216,343 @@ impl<'a> PDF417Encoder<'a> {
             };
         }
 
+        self.padded = 0;
         if right { 
             out[i] = out[i] * 30 + 29;
             i += 1;
+            self.padded = i;
         }
         self.used = i;
         self.last_mode = mode;
 
-        self
+        Some(self)
     }
 
-    /// Appends a special segement crafted to store an __UTF-8__ string `s`.
-    /// __Note that the conversion is space inefficient, if the string is
-    /// composed of ASCII characters, please consider using
-    /// [PDF417Encoder::append_ascii] instead.__ Internally, we use a ECI
-    /// identifier (\\000026) to switch to the UTF-8 code page then append a
-    /// byte segment to store the string's bytes.
-    pub fn append_utf8(mut self, s: &str) -> Self {
-        self.storage[self.used] = ECI_CODE_PAGE; // ECI identifier for code page
-        self.storage[self.used + 1] = 26; // UTF-8 is \000026
+    /// Appends a user defined ECI identifier `id` which must be between
+    /// 810900 and 811799 (inclusive). It is encoded as [ECI_CUSTOM_ID]
+    /// followed by `id - 810900`. The meaning of the data following the ECI
+    /// is up to the application.
+    pub fn append_eci_custom(mut self, id: u32) -> Self {
+        assert!((810900..=811799).contains(&id), "user defined ECI identifiers must be between 810900 and 811799");
+        self.storage[self.used] = ECI_CUSTOM_ID;
+        self.storage[self.used + 1] = (id - 810900) as u16;
         self.used += 2;
-
-        self.append_bytes(s.as_bytes())
+        self
     }
 
-    /// Append a single codeword.
-    pub fn append_codeword(mut self, codeword: u16) -> Self {
-        self.storage[self.used] = codeword;
+    /// Appends the ECI identifier `eci` (code page, general purpose or user
+    /// defined) followed by the ASCII string `s` encoded using the text
+    /// compaction (see [PDF417Encoder::append_ascii]) instead of bytes. This
+    /// is only valid if the characters of `s` have the same values in the
+    /// code page designated by the ECI as in ASCII. Panics if `eci` is
+    /// greater than 811799.
+    pub fn append_eci_then_ascii(mut self, eci: u32, s: &str) -> Self {
+        self = self.append_eci(eci);
+
+        // always latch as the mode after the ECI is not known by the reader
+        self.storage[self.used] = M_LATCH_TEXT;
         self.used += 1;
-        self
+        self.last_mode = Mode::Upper;
+        self.append_ascii(s)
     }
 
-    /// Append a slice of codewords.
-    pub fn append_raw(mut self, codewords: &[u16]) -> Self {
-        self.storage[self.used..self.used+codewords.len()].copy_from_slice(codewords);
-        self.used += codewords.len();
+    /// Appends the ECI identifier `eci` using the shortest form: code page
+    /// (less than 900), general purpose (less than 810900) or user defined.
+    /// Panics if `eci` is greater than 811799.
+    fn append_eci(mut self, eci: u32) -> Self {
+        let base = BASE as u32;
+        let used = self.used;
+        if eci < base {
+            self.storage[used] = ECI_CODE_PAGE;
+            self.storage[used + 1] = eci as u16;
+            self.used += 2;
+        } else if eci < base * (base + 1) {
+            self.storage[used] = ECI_GENERAL_ID;
+            self.storage[used + 1] = (eci / base - 1) as u16;
+            self.storage[used + 2] = (eci % base) as u16;
+            self.used += 3;
+        } else {
+            assert!(eci <= 811799, "ECI identifiers must be at most 811799, got {eci}");
+            self = self.append_eci_custom(eci);
+        }
         self
     }
 
-    /// Call this function to seal your data segments into a slice of codewords
-    /// ready to be rendered to a PDF417. Both padding and ECC codewords are
+    /// Appends the string `s` transcoded to the code page 437 (ECI 2, the
+    /// default code page of old DOS systems) as a byte segment preceded by
+    /// the ECI identifier \\000002.
+    ///
+    /// # Panics
+    /// Panics if a character can not be represented in the code page 437,
+    /// see [PDF417Encoder::try_append_cp437] to handle it as an error or
+    /// [cp437_byte] to check the string beforehand.
+    #[cfg(feature = "cp437")]
+    pub fn append_cp437(self, s: &str) -> Self {
+        match self.try_append_cp437(s) {
+            Ok(encoder) => encoder,
+            Err(e) => panic!("{e}")
+        }
+    }
+
+    /// Same as [PDF417Encoder::append_cp437] but returns a [Cp437Error] if a
+    /// character can not be represented in the code page 437, nothing is
+    /// appended in that case.
+    #[cfg(feature = "cp437")]
+    pub fn try_append_cp437(mut self, s: &str) -> Result<Self, Cp437Error> {
+        let len = s.chars().count();
+        assert!(len <= MAX_BYTES, "too many characters to fit in a symbol");
+        if let Some((index, character)) = s.chars().enumerate().find(|&(_, c)| cp437_byte(c).is_none()) {
+            return Err(Cp437Error { index, character });
+        }
+
+        self = self.append_eci(2); // CP437 is \000002
+        Ok(self.append_byte_iter(len, s.chars().filter_map(cp437_byte)))
+    }
+
+    /// Appends a special segement crafted to store an __UTF-8__ string `s`.
+    /// __Note that the conversion is space inefficient, if the string is
+    /// composed of ASCII characters, please consider using
+    /// [PDF417Encoder::append_ascii] instead.__ Internally, we use a ECI
+    /// identifier (\\000026) to switch to the UTF-8 code page then append a
+    /// byte segment to store the string's bytes.
+    pub fn append_utf8(self, s: &str) -> Self {
+        self.append_eci(26) // UTF-8 is \000026
+            .append_bytes(s.as_bytes())
+    }
+
+    /// Appends an __UTF-8__ string `s` like [PDF417Encoder::append_utf8] but
+    /// the ASCII runs are encoded using the text compaction (see
+    /// [PDF417Encoder::append_ascii]) and only the multibyte characters are
+    /// byte encoded, which is much smaller for mostly ASCII strings. ASCII
+    /// runs shorter than 6 characters between two multibyte characters are
+    /// byte encoded too as switching back and forth would cost more. The
+    /// ECI identifier \\000026 (UTF-8) is appended once before the string.
+    pub fn append_text_smart(mut self, s: &str) -> Self {
+        // below this length an ASCII run is cheaper as bytes than as text
+        // surrounded by the latches to text and back to bytes
+        const MIN_TEXT_RUN: usize = 6;
+
+        self = self.append_eci(26); // UTF-8 is \000026
+
+        // ASCII bytes never appear inside a multibyte sequence, the string
+        // can be split at any ASCII boundary
+        let bytes = s.as_bytes();
+        let mut k = 0;
+        while k < bytes.len() {
+            let mut end = k;
+            if bytes[k].is_ascii() {
+                while end < bytes.len() && bytes[end].is_ascii() {
+                    end += 1;
+                }
+                self = self.append_ascii(&s[k..end]);
+            } else {
+                loop {
+                    while end < bytes.len() && !bytes[end].is_ascii() {
+                        end += 1;
+                    }
+                    let mut next = end;
+                    while next < bytes.len() && bytes[next].is_ascii() {
+                        next += 1;
+                    }
+                    if next < bytes.len() && next - end < MIN_TEXT_RUN {
+                        end = next;
+                    } else {
+                        break;
+                    }
+                }
+                self = self.append_bytes(&bytes[k..end]);
+            }
+            k = end;
+        }
+        self
+    }
+
+    /// Executes the encoding `plan` literally, operation by operation,
+    /// without inserting any latch or shift, e.g. to reproduce the exact
+    /// codewords of another encoder. The plan must be valid, only the text
+    /// values (less than 30) and the digits are checked. After a
+    /// [Op::Text] operation, the encoder is in the text sub-mode its values
+    /// end in, so the following text segments continue from there.
+    pub fn append_plan(mut self, plan: &[Op]) -> Self {
+        for op in plan {
+            self.padded = 0;
+            self = match *op {
+                Op::LatchText => self.append_latch_text(),
+                Op::LatchByte => self.append_latch_byte(),
+                Op::LatchByteM6 => {
+                    self.last_mode = Mode::Byte;
+                    self.append_raw(&[M_LATCH_BYTE_M6])
+                },
+                Op::LatchNumeric => self.append_latch_numeric(),
+                Op::ShiftByte(byte) => self.append_raw(&[M_SHIFT_BYTE, byte as u16]),
+                Op::Text(values) => {
+                    assert!(values.iter().all(|&v| v < 30), "text values must be less than 30");
+                    for pair in values.chunks(2) {
+                        let right = pair.get(1).copied().unwrap_or(29);
+                        self.storage[self.used] = pair[0] as u16 * 30 + right as u16;
+                        self.used += 1;
+                    }
+                    if values.len() % 2 == 1 {
+                        self.padded = self.used;
+                    }
+                    let mode = if self.last_mode.is_text() { self.last_mode } else { Mode::Upper };
+                    self.last_mode = submode_after(mode, values);
+                    self
+                },
+                Op::Digits(digits) => {
+                    assert!(digits.iter().all(u8::is_ascii_digit), "numeric segments can only contain ASCII digits");
+                    for group in digits.chunks(44) {
+                        self.used += compact_numeric(&mut self.storage[self.used..], group);
+                    }
+                    self
+                },
+                Op::Bytes(bytes) => {
+                    self.used += compact_bytes(&mut self.storage[self.used..], bytes.len(), bytes.iter().copied());
+                    self
+                },
+                Op::Raw(codewords) => self.append_raw(codewords),
+            };
+        }
+        self
+    }
+
+    /// Appends [M_LATCH_TEXT], the following text segments start in the
+    /// upper case submode without latching again.
+    pub fn append_latch_text(mut self) -> Self {
+        self = self.append_codeword(M_LATCH_TEXT);
+        self.last_mode = Mode::Upper;
+        self
+    }
+
+    /// Appends [M_LATCH_BYTE], the following codewords are interpreted as
+    /// bytes (up to the caller, e.g. with [PDF417Encoder::append_raw]).
+    pub fn append_latch_byte(mut self) -> Self {
+        self = self.append_codeword(M_LATCH_BYTE);
+        self.last_mode = Mode::Byte;
+        self
+    }
+
+    /// Appends [M_LATCH_NUMERIC], the following numeric segments do not
+    /// latch again.
+    pub fn append_latch_numeric(mut self) -> Self {
+        self = self.append_codeword(M_LATCH_NUMERIC);
+        self.last_mode = Mode::Numeric;
+        self
+    }
+
+    /// Appends [M_SHIFT_BYTE] followed by `byte`. The byte shift is only
+    /// valid in the text compaction, the text mode is kept afterwards.
+    pub fn append_shift_byte(self, byte: u8) -> Self {
+        assert!(self.last_mode.is_text(), "the byte shift is only valid in the text compaction");
+        self.append_raw(&[M_SHIFT_BYTE, byte as u16])
+    }
+
+    /// Append a single codeword. Panics if the codeword is not less than
+    /// 929, see [PDF417Encoder::try_append_codeword].
+    pub fn append_codeword(self, codeword: u16) -> Self {
+        match self.try_append_codeword(codeword) {
+            Ok(encoder) => encoder,
+            Err(e) => panic!("{e}")
+        }
+    }
+
+    /// Same as [PDF417Encoder::append_codeword] but returns a
+    /// [CodewordError] if the codeword is not less than 929. The control
+    /// codewords (900 to 928) are accepted.
+    pub fn try_append_codeword(self, codeword: u16) -> Result<Self, CodewordError> {
+        self.try_append_raw(&[codeword])
+    }
+
+    /// Append a slice of codewords. Panics if a codeword is not less than
+    /// 929, see [PDF417Encoder::try_append_raw].
+    pub fn append_raw(self, codewords: &[u16]) -> Self {
+        match self.try_append_raw(codewords) {
+            Ok(encoder) => encoder,
+            Err(e) => panic!("{e}")
+        }
+    }
+
+    /// Same as [PDF417Encoder::append_raw] but returns a [CodewordError] if
+    /// a codeword is not less than 929 (e.g. a computed value that
+    /// overflowed), nothing is appended in that case. The control codewords
+    /// (900 to 928) are accepted, their placement is up to the caller.
+    pub fn try_append_raw(mut self, codewords: &[u16]) -> Result<Self, CodewordError> {
+        if let Some(index) = codewords.iter().position(|&cw| cw >= GF_MODULUS) {
+            return Err(CodewordError { index, codeword: codewords[index] });
+        }
+        self.storage[self.used..self.used+codewords.len()].copy_from_slice(codewords);
+        self.used += codewords.len();
+        Ok(self)
+    }
+
+    /// Appends numeric codewords already compacted (base 900 groups as
+    /// emitted by [PDF417Encoder::append_num] after the latch), e.g. a cached
+    /// field. The numeric latch is inserted if the encoder is not already in
+    /// the numeric mode. Panics if a codeword is not less than 900.
+    pub fn append_numeric_raw(mut self, codewords: &[u16]) -> Self {
+        assert!(codewords.iter().all(|&cw| cw < BASE), "numeric codewords must be less than 900");
+        if self.last_mode != Mode::Numeric {
+            self = self.append_latch_numeric();
+        }
+        self.append_raw(codewords)
+    }
+
+    /// Appends the data codewords already compacted by another regular
+    /// encoder, e.g. the payload of a Macro PDF417 segment built separately
+    /// (`&other.as_codewords()[1..]`, skipping the length slot). Panics if a
+    /// codeword is not a valid codeword (929 or more) or is reserved for the
+    /// Macro PDF417 control block (922, 923 and 928), latches and shifts are
+    /// accepted. As the payload starts in the initial text mode of an
+    /// encoder, a text latch is inserted if needed.
+    pub fn append_segment_payload(mut self, codewords: &[u16]) -> Self {
+        assert!(codewords.iter().all(|&cw| cw < GF_MODULUS && !MACRO_CODEWORDS.contains(&cw)),
+            "the payload must only contain data codewords");
+        if codewords.first().is_some_and(|&cw| cw < BASE) && self.last_mode != Mode::Upper {
+            self = self.append_codeword(M_LATCH_TEXT);
+        }
+        self = self.append_raw(codewords);
+        // the mode at the end of the payload is unknown, the next segment
+        // latches explicitly
+        self.last_mode = Mode::Byte;
+        self
+    }
+
+    /// Call this function to seal your data segments into a slice of codewords
+    /// ready to be rendered to a PDF417. Both padding and ECC codewords are
     /// generated by this function. Be careful, when generating a MicroPDF417
     /// the `val` parameter represents the variant, otherwise the `val`
-    /// parameter represents the ECC level.
+    /// parameter represents the ECC level. Panics if the configuration is
+    /// invalid, see [PDF417Encoder::try_seal].
     pub fn seal(self, val: u8) -> &'a mut [u16] {
+        match self.try_seal(val) {
+            Ok(storage) => storage,
+            Err(e) => panic!("{e}")
+        }
+    }
+
+    /// Same as [PDF417Encoder::seal] but returns a [SealError] instead of
+    /// panicking when the variant or the ECC level is invalid or when the
+    /// data and ECC codewords do not fit in the storage.
+    pub fn try_seal(self, val: u8) -> Result<&'a mut [u16], SealError> {
         if self.micro {
+            use crate::tables::*;
             let variant = val as usize;
-            assert!(variant <= 34, "invalid variant (0-34)");
+            if variant >= M_PDF417_VARIANTS_COUNT {
+                return Err(SealError::InvalidVariant(val));
+            }
 
-            use crate::tables::*;
             let (count, offset) = (
                 M_PDF417_VARIANTS[2 * M_PDF417_VARIANTS_COUNT + variant] as usize,
                 M_PDF417_VARIANTS[3 * M_PDF417_VARIANTS_COUNT + variant] as usize
             );
+            if count > self.capacity() {
+                return Err(SealError::EccOverflow { ecc: count, capacity: self.capacity() });
+            }
 
             let total = self.capacity() - count;
+            if self.used > total {
+                return Err(SealError::MicroDataOverflow { data: self.used, capacity: total, variant: val });
+            }
             if self.used < total {
                 self.storage[self.used..total].fill(CW_PADDING);
             }
@@ -438,8 +1560,23 @@ impl<'a> PDF417Encoder<'a> {
             ecc::generate_micro_ecc(self.storage, count, offset);
         } else {
             let level = val;
-            let total = self.capacity() - ecc::ecc_count(level);
-            self.storage[0] = total as u16;
+            if level > 8 {
+                return Err(SealError::InvalidLevel(level));
+            }
+
+            // the length codeword must fit alongside the ECC codewords
+            let ecc = ecc::ecc_count(level);
+            if ecc >= self.capacity() {
+                return Err(SealError::EccOverflow { ecc, capacity: self.capacity() });
+            }
+
+            let total = self.capacity() - ecc;
+            if self.used > total {
+                return Err(SealError::DataOverflow { data: self.used, capacity: total, level });
+            }
+            if !self.raw {
+                self.storage[0] = total as u16;
+            }
             if self.used < total {
                 self.storage[self.used..total].fill(CW_PADDING);
             }
@@ -447,12 +1584,12 @@ impl<'a> PDF417Encoder<'a> {
             ecc::generate_ecc(self.storage, level);
         }
 
-        self.storage
+        Ok(self.storage)
     }
 
     /// Automatically try to fit the maximum number of ECC codewords depending
     /// on the remaining codeword slots. Be careful, when generating a
-    /// MicroPDF417, the returned byte represents a variant number (0-34),
+    /// MicroPDF417, the returned byte represents a variant number (0-33),
     /// otherwise its returns the ECC level (0-8). If there is not enough
     /// space for the minimum amount of ECC codewords or a big-enough variant
     /// does not exist, None is returned.
@@ -473,6 +1610,14 @@ impl<'a> PDF417Encoder<'a> {
         }
     }
 
+    /// Same as [PDF417Encoder::fit_ecc] for a regular PDF417 but never
+    /// returns a level above `max_level`, e.g. to keep the symbol density
+    /// reasonable.
+    pub fn fit_ecc_capped(&self, max_level: u8) -> Option<u8> {
+        assert!(!self.micro, "fit_ecc_capped requires an encoder created with micro = false");
+        self.fit_ecc().map(|level| level.min(max_level))
+    }
+
     /// Call this function to seal your data segments into a slice of codewords
     /// ready to be rendered to a PDF417. Both padding and ECC codewords are
     /// generated by this function. This internally uses
@@ -482,10 +1627,545 @@ impl<'a> PDF417Encoder<'a> {
         let v = self.fit_ecc()?;
         Some((v, self.seal(v)))
     }
+
+    /// MicroPDF417 counterpart of [PDF417Encoder::fit_seal]: selects the
+    /// smallest variant able to hold the data segments (see
+    /// [PDF417Encoder::fit_ecc]) and seals them. The returned slice is the
+    /// beginning of the storage sized for the variant (rows \* cols). None is
+    /// returned if no variant can hold the data or if the storage is too
+    /// small for the selected variant.
+    pub fn fit_seal_micro(mut self) -> Option<(Variant, &'a mut [u16])> {
+        assert!(self.micro, "fit_seal_micro requires an encoder created with micro = true");
+        let variant = Variant::from(self.fit_ecc()?);
+        let total = variant.rows() as usize * variant.cols() as usize;
+        if total > self.capacity() {
+            return None;
+        }
+
+        let storage = core::mem::take(&mut self.storage);
+        self.storage = &mut storage[..total];
+        Some((variant, self.seal(variant.into())))
+    }
+
+    /// Seals the data segments into a newly allocated symbol of minimal
+    /// dimensions and returns `(codewords, (rows, cols), val)`, everything
+    /// needed to call [crate::PDF417::new] (or
+    /// [crate::MicroPDF417::from_variant] for MicroPDF417 where `val` is the
+    /// variant). For regular PDF417, the dimensions are chosen using
+    /// [best_dimensions] to hold at least the ECC level recommended by the
+    /// specification, the ECC level is then maximized using
+    /// [PDF417Encoder::fit_ecc]. None is returned if the data does not fit in
+    /// a symbol.
+    #[cfg(feature = "alloc")]
+    pub fn auto_render(self) -> Option<(alloc::vec::Vec<u16>, (u8, u8), u8)> {
+        let ((rows, cols), ecc) = if self.micro {
+            let variant = Variant::from(self.fit_ecc()?);
+            ((variant.rows(), variant.cols()), 0)
+        } else {
+            let ecc = ecc::ecc_count(recommended_level(self.used));
+            (best_dimensions(self.used + ecc)?, ecc)
+        };
+
+        let mut storage = alloc::vec![0u16; rows as usize * cols as usize];
+        if self.used + ecc > storage.len() {
+            return None;
+        }
+        storage[..self.used].copy_from_slice(self.as_codewords());
+
+        let encoder = PDF417Encoder { storage: &mut storage, ..self };
+        let (val, _) = encoder.fit_seal()?;
+        Some((storage, (rows, cols), val))
+    }
+}
+
+/// A [PDF417Encoder] owning a fixed-size array of `N` codewords. The size
+/// of the symbol is part of the type which allows the ECC level to be checked
+/// against `N` at compile time (see [PDF417ArrayEncoder::seal]).
+///
+/// ```
+/// # use pdf417::*;
+/// const ROWS: u8 = 5;
+/// const COLS: u8 = 3;
+///
+/// let codewords = PDF417ArrayEncoder::<{ (ROWS * COLS) as usize }>::new(false)
+///     .append(|e| e.append_ascii("Hello"))
+///     .seal::<2>();
+/// let pdf417 = PDF417::new(&codewords, ROWS, COLS, 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PDF417ArrayEncoder<const N: usize> {
+    storage: [u16; N],
+    state: EncoderState,
+    micro: bool,
+    options: Options,
+}
+
+/// Compile time checks of [PDF417ArrayEncoder::seal], evaluated when the
+/// method is instantiated.
+struct SealCheck<const N: usize, const LEVEL: u8>;
+
+impl<const N: usize, const LEVEL: u8> SealCheck<N, LEVEL> {
+    const VALID: () = {
+        assert!(LEVEL < 9, "ECC level must be between 0 and 8");
+        assert!(ecc::ecc_count(LEVEL) < N, "The ECC codewords do not fit in the array");
+    };
+}
+
+impl<const N: usize> PDF417ArrayEncoder<N> {
+    /// Creates an encoder with an array of `N` codewords. Set `micro` to true
+    /// to encode according to the MicroPDF417 specification.
+    pub const fn new(micro: bool) -> Self {
+        assert!(N > 0, "storage must be able to contain at least one codeword");
+        let state = if micro {
+            EncoderState { used: 0, last_mode: Mode::Byte, padded: 0 }
+        } else {
+            EncoderState { used: 1, last_mode: Mode::Upper, padded: 0 }
+        };
+        Self { storage: [0; N], state, micro, options: Options::DEFAULT }
+    }
+
+    /// Returns the number of codewords already used
+    pub const fn count(&self) -> usize {
+        self.state.used
+    }
+
+    /// Returns the total number of codewords of the array (`N`).
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Appends data segments using a borrowed [PDF417Encoder].
+    pub fn append<F>(mut self, f: F) -> Self
+    where F: for<'b> FnOnce(PDF417Encoder<'b>) -> PDF417Encoder<'b> {
+        let encoder = f(self.encoder());
+        (self.state, self.options) = (encoder.snapshot(), encoder.options);
+        self
+    }
+
+    /// Seals the data segments of a regular PDF417 with the ECC level
+    /// `LEVEL`. The ECC codewords and the length codeword must fit in `N`
+    /// codewords, otherwise the compilation fails:
+    ///
+    /// ```compile_fail
+    /// # use pdf417::*;
+    /// // 512 ECC codewords do not fit in 15 codewords
+    /// PDF417ArrayEncoder::<15>::new(false).seal::<8>();
+    /// ```
+    pub fn seal<const LEVEL: u8>(self) -> [u16; N] {
+        let () = SealCheck::<N, LEVEL>::VALID;
+        assert!(!self.micro, "use try_seal to seal a MicroPDF417");
+        match self.try_seal(LEVEL) {
+            Ok(storage) => storage,
+            Err(e) => panic!("{e}")
+        }
+    }
+
+    /// See [PDF417Encoder::try_seal].
+    pub fn try_seal(mut self, val: u8) -> Result<[u16; N], SealError> {
+        self.encoder().try_seal(val)?;
+        Ok(self.storage)
+    }
+
+    fn encoder(&mut self) -> PDF417Encoder<'_> {
+        PDF417Encoder {
+            storage: &mut self.storage,
+            used: self.state.used,
+            micro: self.micro,
+            last_mode: self.state.last_mode,
+            padded: self.state.padded,
+            raw: false,
+            options: self.options,
+        }
+    }
+}
+
+/// Encodes several symbols back to back into a single backing storage, e.g.
+/// a static arena. Each symbol gets its own slice of the storage holding its
+/// length codeword, data and ECC codewords, so the symbols can be rendered
+/// independently using the `(offset, len)` returned by
+/// [BatchEncoder::encode].
+///
+/// ```rust
+/// # use pdf417::*;
+/// let mut arena = [0u16; 64];
+/// let mut batch = BatchEncoder::new(&mut arena, false);
+/// let first = batch.encode(3 * 4, 1, |e| e.append_ascii("Label 1")).unwrap();
+/// let second = batch.encode(3 * 4, 1, |e| e.append_ascii("Label 2")).unwrap();
+///
+/// let (offset, len) = second;
+/// let pdf417 = PDF417::new(&arena[offset..offset + len], 3, 4, 1);
+/// # let _ = (first, pdf417);
+/// ```
+#[derive(Debug)]
+pub struct BatchEncoder<'a> {
+    storage: &'a mut [u16],
+    used: usize,
+    micro: bool,
+}
+
+impl<'a> BatchEncoder<'a> {
+    /// Creates a batch encoder using `storage` as backing storage. Set
+    /// `micro` to true to encode MicroPDF417 symbols.
+    pub fn new(storage: &'a mut [u16], micro: bool) -> Self {
+        Self { storage, used: 0, micro }
+    }
+
+    /// Returns the number of codewords used by the symbols already encoded.
+    pub fn count(&self) -> usize {
+        self.used
+    }
+
+    /// Returns the number of codewords still available for new symbols.
+    pub fn remaining(&self) -> usize {
+        self.storage.len() - self.used
+    }
+
+    /// Encodes a symbol of `len` codewords (rows \* cols) with the data
+    /// segments appended by `f` and seals it with `val` (see
+    /// [PDF417Encoder::seal]). Returns the `(offset, len)` of the symbol in
+    /// the backing storage. The codewords are not reserved if sealing fails.
+    /// Panics if the symbol does not fit in the remaining storage.
+    pub fn encode<F>(&mut self, len: usize, val: u8, f: F) -> Result<(usize, usize), SealError>
+    where F: for<'b> FnOnce(PDF417Encoder<'b>) -> PDF417Encoder<'b> {
+        assert!(len <= self.remaining(), "the symbol does not fit in the remaining storage");
+        let offset = self.used;
+        let storage = &mut self.storage[offset..offset + len];
+        f(PDF417Encoder::new(storage, self.micro)).try_seal(val)?;
+        self.used += len;
+        Ok((offset, len))
+    }
+}
+
+/// Destination of the codewords produced by a [PDF417SinkEncoder], e.g. a
+/// buffer or a channel to a co-processor.
+pub trait CodewordSink {
+    /// Error returned when a codeword can not be pushed.
+    type Error;
+
+    /// Pushes the codeword `cw` after the ones already pushed.
+    fn push(&mut self, cw: u16) -> Result<(), Self::Error>;
+}
+
+/// Error returned when pushing to a full `&mut [u16]` sink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SinkFullError;
+
+impl fmt::Display for SinkFullError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the sink is full")
+    }
+}
+
+/// Writes the codewords at the start of the slice which is then advanced past
+/// them.
+impl CodewordSink for &mut [u16] {
+    type Error = SinkFullError;
+
+    fn push(&mut self, cw: u16) -> Result<(), Self::Error> {
+        let (first, rest) = core::mem::take(self).split_first_mut().ok_or(SinkFullError)?;
+        *first = cw;
+        *self = rest;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl CodewordSink for alloc::vec::Vec<u16> {
+    type Error = core::convert::Infallible;
+
+    fn push(&mut self, cw: u16) -> Result<(), Self::Error> {
+        alloc::vec::Vec::push(self, cw);
+        Ok(())
+    }
+}
+
+/// A [PDF417Encoder] pushing the data codewords to a [CodewordSink] as the
+/// segments are appended instead of keeping them. Each segment is encoded in
+/// a scratch array of `N` codewords which must be large enough to hold the
+/// biggest segment. The length codeword of a regular PDF417 is not pushed as
+/// it is only known once all the data is encoded.
+///
+/// ```
+/// # use pdf417::*;
+/// let mut codewords = [0u16; 16];
+/// let remaining = PDF417SinkEncoder::<_, 8>::new(&mut codewords[..], false)
+///     .append(|e| e.append_ascii("Hello"))
+///     .and_then(|e| e.append(|e| e.append_num(1234)))
+///     .and_then(|e| e.finish())
+///     .unwrap();
+/// let len = 16 - remaining.len();
+/// # assert_eq!(len, 6);
+/// ```
+#[derive(Debug)]
+pub struct PDF417SinkEncoder<S, const N: usize> {
+    sink: S,
+    scratch: [u16; N],
+    state: EncoderState,
+    micro: bool,
+    options: Options,
+    pushed: usize,
+}
+
+impl<S: CodewordSink, const N: usize> PDF417SinkEncoder<S, N> {
+    /// Creates an encoder pushing to `sink` with a scratch array of `N`
+    /// codewords. Set `micro` to true to encode according to the
+    /// MicroPDF417 specification.
+    pub fn new(sink: S, micro: bool) -> Self {
+        assert!(N > 0, "storage must be able to contain at least one codeword");
+        let last_mode = if micro { Mode::Byte } else { Mode::Upper };
+        let state = EncoderState { used: 0, last_mode, padded: 0 };
+        Self { sink, scratch: [0; N], state, micro, options: Options::DEFAULT, pushed: 0 }
+    }
+
+    /// Returns the number of data codewords encoded so far (the last one may
+    /// not be pushed yet, see [PDF417SinkEncoder::finish]).
+    pub fn count(&self) -> usize {
+        self.pushed + self.state.used
+    }
+
+    /// Appends data segments using a borrowed [PDF417Encoder] then pushes
+    /// the resulting codewords to the sink.
+    pub fn append<F>(mut self, f: F) -> Result<Self, S::Error>
+    where F: for<'b> FnOnce(PDF417Encoder<'b>) -> PDF417Encoder<'b> {
+        let encoder = f(PDF417Encoder {
+            storage: &mut self.scratch,
+            used: self.state.used,
+            micro: self.micro,
+            last_mode: self.state.last_mode,
+            padded: self.state.padded,
+            raw: false,
+            options: self.options,
+        });
+        (self.state, self.options) = (encoder.snapshot(), encoder.options);
+
+        // a padded text codeword is modified if the next segment is text
+        let used = self.state.used;
+        let held = if self.state.padded == used && used > 0 { 1 } else { 0 };
+        for &cw in &self.scratch[..used - held] {
+            self.sink.push(cw)?;
+        }
+        self.pushed += used - held;
+        self.scratch[0] = self.scratch[used - held];
+        self.state.used = held;
+        self.state.padded = held;
+        Ok(self)
+    }
+
+    /// Pushes the remaining codeword (if any) and returns the sink.
+    pub fn finish(mut self) -> Result<S, S::Error> {
+        for &cw in &self.scratch[..self.state.used] {
+            self.sink.push(cw)?;
+        }
+        Ok(self.sink)
+    }
+}
+
+/// Error returned when decoding high level codewords.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The output buffer is too small to hold the decoded data.
+    BufferTooSmall,
+    /// The codeword at `index` is invalid or not supported.
+    InvalidCodeword { index: usize, codeword: u16 },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DecodeError::BufferTooSmall =>
+                write!(f, "the output buffer is too small"),
+            DecodeError::InvalidCodeword { index, codeword } =>
+                write!(f, "invalid codeword {codeword} at index {index}"),
+        }
+    }
+}
+
+struct Output<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+}
+
+impl Output<'_> {
+    fn push(&mut self, b: u8) -> Result<(), DecodeError> {
+        *self.buf.get_mut(self.len).ok_or(DecodeError::BufferTooSmall)? = b;
+        self.len += 1;
+        Ok(())
+    }
+}
+
+/// Decodes the high level data codewords `codewords` (without the length
+/// and ECC codewords, the padding codewords are ignored) into `buf`, e.g.
+/// after correcting them with [ecc::decode]. This is the inverse of the
+/// `append_*` methods of [PDF417Encoder]: the text, byte and numeric
+/// compactions are supported, the ECI identifiers are skipped and the bytes
+/// are written as is (an UTF-8 string appended with
+/// [PDF417Encoder::append_utf8] is thus decoded to its UTF-8 bytes). Returns
+/// the number of bytes written.
+///
+/// ```rust
+/// # use pdf417::*;
+/// let mut storage = [0u16; 5 * 3];
+/// let sealed = PDF417Encoder::new(&mut storage, false).append_ascii("Hi").append_num(42).seal(1);
+/// let mut buf = [0u8; 16];
+/// let len = pdf417::high_level::decode(&sealed[1..sealed[0] as usize], &mut buf).unwrap();
+/// assert_eq!(&buf[..len], b"Hi42");
+/// ```
+pub fn decode(codewords: &[u16], buf: &mut [u8]) -> Result<usize, DecodeError> {
+    let mut out = Output { buf, len: 0 };
+    let mut submode = Mode::Upper;
+    let mut shift: Option<Mode> = None;
+    let mut i = 0;
+
+    let invalid = |index: usize| DecodeError::InvalidCodeword { index, codeword: codewords[index] };
+
+    while i < codewords.len() {
+        match codewords[i] {
+            M_LATCH_TEXT => {
+                submode = Mode::Upper;
+                shift = None;
+                i += 1;
+            },
+            cw @ (M_LATCH_BYTE | M_LATCH_BYTE_M6) => {
+                i = decode_bytes(codewords, i + 1, cw == M_LATCH_BYTE_M6, &mut out)?;
+            },
+            M_LATCH_NUMERIC => {
+                i = decode_numeric(codewords, i + 1, &mut out)?;
+            },
+            M_SHIFT_BYTE => {
+                // a pending shift (the padding of the previous codeword) is
+                // consumed by the byte shift, the submode is unchanged
+                shift = None;
+                match codewords.get(i + 1) {
+                    Some(&b) if b < 256 => out.push(b as u8)?,
+                    Some(_) => return Err(invalid(i + 1)),
+                    None => return Err(invalid(i)),
+                }
+                i += 2;
+            },
+            // the encoded bytes are written as is whatever the ECI
+            ECI_CODE_PAGE | ECI_CUSTOM_ID => i += 2,
+            ECI_GENERAL_ID => i += 3,
+            cw if cw < BASE => {
+                for v in [(cw / 30) as u8, (cw % 30) as u8] {
+                    decode_text_value(v, &mut submode, &mut shift, &mut out)?;
+                }
+                i += 1;
+            },
+            _ => return Err(invalid(i)),
+        }
+    }
+
+    Ok(out.len)
+}
+
+/// Same as [decode] but returns the decoded data as a string for display,
+/// e.g. to preview the content of a symbol. The bytes which are not part of
+/// a valid UTF-8 sequence (binary data or a code page other than UTF-8) are
+/// replaced by `?` in `buf`.
+///
+/// ```rust
+/// # use pdf417::*;
+/// let mut storage = [0u16; 8];
+/// let ec = PDF417Encoder::new(&mut storage, false).append_ascii("N").append_num(1234567);
+/// let mut buf = [0u8; 16];
+/// let text = pdf417::high_level::decode_to_str(&ec.as_codewords()[1..], &mut buf).unwrap();
+/// assert_eq!(text, "N1234567");
+/// ```
+pub fn decode_to_str<'b>(codewords: &[u16], buf: &'b mut [u8]) -> Result<&'b str, DecodeError> {
+    let len = decode(codewords, buf)?;
+    let mut bytes = &mut buf[..len];
+    while let Err(e) = core::str::from_utf8(bytes) {
+        let invalid = e.valid_up_to();
+        let count = e.error_len().unwrap_or(bytes.len() - invalid);
+        bytes[invalid..invalid + count].fill(b'?');
+        bytes = &mut bytes[invalid + count..];
+    }
+    Ok(core::str::from_utf8(&buf[..len]).expect("the invalid sequences are replaced"))
+}
+
+fn decode_text_value(v: u8, submode: &mut Mode, shift: &mut Option<Mode>, out: &mut Output) -> Result<(), DecodeError> {
+    let shifted = shift.is_some();
+    let mode = shift.take().unwrap_or(*submode);
+    let c = match (mode, v) {
+        (Mode::Upper, 0..=25) => b'A' + v,
+        (Mode::Lower, 0..=25) => b'a' + v,
+        (Mode::Upper | Mode::Lower | Mode::Mixed, 26) => b' ',
+        (Mode::Mixed, 0..=9) => b'0' + v,
+        (Mode::Mixed, 10..=24) => MIXED_CHAR_SET[v as usize - 10],
+        (Mode::Punctuation, 0..=28) => PUNC_CHAR_SET[v as usize],
+        // latch to upper (also valid when the punctuation mode is shifted)
+        (Mode::Punctuation, 29) | (Mode::Mixed, 28) => { *submode = Mode::Upper; return Ok(()) },
+        _ if shifted => return Ok(()),
+        (Mode::Upper | Mode::Mixed, 27) => { *submode = Mode::Lower; return Ok(()) },
+        (Mode::Lower, 27) => { *shift = Some(Mode::Upper); return Ok(()) },
+        (Mode::Upper | Mode::Lower, 28) => { *submode = Mode::Mixed; return Ok(()) },
+        (Mode::Mixed, 25) => { *submode = Mode::Punctuation; return Ok(()) },
+        (_, 29) => { *shift = Some(Mode::Punctuation); return Ok(()) },
+        _ => unreachable!("Unknown text value {v} in mode {mode:?}"),
+    };
+    out.push(c)
+}
+
+fn decode_bytes(codewords: &[u16], start: usize, m6: bool, out: &mut Output) -> Result<usize, DecodeError> {
+    let mut end = start;
+    while end < codewords.len() && codewords[end] < BASE {
+        end += 1;
+    }
+    let segment = &codewords[start..end];
+
+    // With M_LATCH_BYTE, the last group is always made of single bytes
+    let groups = if m6 || segment.is_empty() { segment.len() / 5 } else { (segment.len() - 1) / 5 };
+    for group in segment[..groups * 5].chunks(5) {
+        let s = group.iter().fold(0u64, |s, &cw| s * BASE as u64 + cw as u64);
+        for n in (0..6).rev() {
+            out.push((s >> (n * 8)) as u8)?;
+        }
+    }
+    for (k, &cw) in segment[groups * 5..].iter().enumerate() {
+        if cw >= 256 {
+            return Err(DecodeError::InvalidCodeword { index: start + groups * 5 + k, codeword: cw });
+        }
+        out.push(cw as u8)?;
+    }
+
+    Ok(end)
+}
+
+fn decode_numeric(codewords: &[u16], start: usize, out: &mut Output) -> Result<usize, DecodeError> {
+    let mut end = start;
+    while end < codewords.len() && codewords[end] < BASE {
+        end += 1;
+    }
+
+    for (g, group) in codewords[start..end].chunks(15).enumerate() {
+        let mut b = U160::zero();
+        for &cw in group {
+            b.digit_cin_mul_(cw as usize, BASE as usize);
+        }
+
+        let mut digits = [0u8; 45];
+        let mut count = 0;
+        while !b.is_zero() {
+            digits[count] = b'0' + b.digit_udivide_inplace_(10).expect("10 > 0") as u8;
+            count += 1;
+        }
+        // skip the leading 1
+        if count == 0 || digits[count - 1] != b'1' {
+            let index = start + g * 15;
+            return Err(DecodeError::InvalidCodeword { index, codeword: codewords[index] });
+        }
+        for &d in digits[..count - 1].iter().rev() {
+            out.push(d)?;
+        }
+    }
+
+    Ok(end)
 }
 
 #[cfg(test)]
 mod tests {
+    extern crate std;
+
     use super::PDF417Encoder;
 
     #[test]
@@ -497,6 +2177,25 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::identity_op, clippy::erasing_op)]
+    fn test_encode_ascii_isolated_whitespace() {
+        // A ps \t B ps \t C ps \n (was A ml \t al B ml \t al C ps \n)
+        let mut codewords = [0u16; 6];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_ascii("A\tB\tC\n");
+        assert_eq!(ec.used, codewords.len());
+        assert_eq!(&codewords, &[0, 0 * 30 + 29, 12 * 30 + 1, 29 * 30 + 12, 2 * 30 + 29, 15 * 30 + 29]);
+        assert!(roundtrip("A\tB\tC\n", 2));
+
+        // followed by mixed characters, latching to mixed stays cheaper
+        let mut codewords = [0u16; 5];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_ascii("a\t1\t2");
+        assert_eq!(ec.used, codewords.len());
+        assert!(roundtrip("a\t1\t2", 2));
+        assert!(roundtrip("Street 1,\r\nCity: Paris\r\n\tFrance.", 2));
+    }
+
+    #[test]
+    #[allow(clippy::identity_op)]
     fn test_generate_ascii_switch_modes() {
         let mut codewords = [0u16; 9];
         let ec = PDF417Encoder::new(&mut codewords, false).append_ascii("abc1D234\x1B");
@@ -531,40 +2230,672 @@ mod tests {
     }
 
     #[test]
-    fn test_generate_ascii_with_digits() {
-        let mut codewords = [0u16; 17];
-        let ec = PDF417Encoder::new(&mut codewords, false).append_ascii("encoded 0123456789 as digits");
-        assert_eq!(ec.used, codewords.len());
-        assert_eq!(&codewords, &[0, 27 * 30 + 4, 13 * 30 + 2, 14 * 30 + 3, 4 * 30 + 3, 26 * 30 + 28, 0 * 30 + 1, 2 * 30 + 3, 4 * 30 + 5, 6 * 30 + 7, 8 * 30 + 9,
-            26 * 30 + 27, 0 * 30 + 18, 26 * 30 + 3, 8 * 30 + 6, 8 * 30 + 19, 18 * 30 + 29]);
+    fn test_encode_int() {
+        // same codewords as the numeric compaction of the decimal digits
+        fn check<T: super::NumericInt>(n: T, digits: &str) {
+            let mut a = [0u16; 32];
+            let mut b = [0u16; 32];
+            let count = PDF417Encoder::new(&mut a, false).append_int(n).count();
+            PDF417Encoder::new(&mut b, false).append_numeric_bytes(digits.as_bytes());
+            assert_eq!(a[..count], b[..count], "{digits}");
+            assert!(roundtrip_with(|e| e.append_int(n), digits.as_bytes(), 2));
+        }
+
+        check(7u8, "7");
+        check(u16::MAX, "65535");
+        check(123456u32, "123456");
+        check(u64::MAX, "18446744073709551615");
+        check(42usize, "42");
+        check(u64::try_from(12i32).unwrap(), "12");
+        check(10u128.pow(37) + 1, "10000000000000000000000000000000000001");
+        check(u128::MAX, "340282366920938463463374607431768211455");
+        check(0u128, "0");
     }
 
     #[test]
-    fn test_generate_ascii_punc_mixed() {
-        let mut codewords = [0u16; 18];
-        let ec = PDF417Encoder::new(&mut codewords, false).append_ascii("This! Is a `quote (100%)`.");
-        assert_eq!(ec.used, codewords.len());
-        assert_eq!(&codewords, &[0, 19 * 30 + 27, 7 * 30 + 8, 18 * 30 + 29, 10 * 30 + 26, 27 * 30 + 8, 18 * 30 + 26, 0 * 30 + 26, 29 * 30 + 8, 16 * 30 + 20, 14 * 30 + 19, 4 * 30 + 26, 29 * 30 + 23, 28 * 30 + 1, 0 * 30 + 0, 21 * 30 + 25, 24 * 30 + 8, 17 * 30 + 29]);
+    fn test_encode_num_max() {
+        let mut codewords = [0u16; 16];
+        // 20 digits: 20 / 3 + 1 codewords
+        let ec = PDF417Encoder::new(&mut codewords, false).append_num(u64::MAX);
+        assert_eq!(ec.count(), 2 + 7);
+        assert!(roundtrip_with(|e| e.append_num(u64::MAX), b"18446744073709551615", 0));
+        assert!(roundtrip_with(|e| e.append_int(u128::MAX), b"340282366920938463463374607431768211455", 0));
+        assert!(roundtrip_with(|e| e.append_int(10u128.pow(38) - 1), &[b'9'; 38], 0));
     }
 
     #[test]
-    fn test_encode_bytes_multiple() {
-        let mut codewords = [0u16; 7];
-        let ec = PDF417Encoder::new(&mut codewords, false).append_bytes(b"alcool");
-        assert_eq!(ec.used, codewords.len());
-        assert_eq!(&codewords, &[0, 924, 163, 238, 432, 766, 244]);
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "the numeric segment of 7 codewords does not fit in the storage")]
+    fn test_encode_num_overflow() {
+        let mut codewords = [0u16; 8];
+        PDF417Encoder::new(&mut codewords, false).append_num(u64::MAX);
     }
 
     #[test]
-    fn test_encode_bytes_not_multiple() {
-        let mut codewords = [0u16; 11];
-        let ec = PDF417Encoder::new(&mut codewords, false).append_bytes(b"encode bin");
-        assert_eq!(ec.used, codewords.len());
-        assert_eq!(&codewords, &[0, 901, 169, 883, 224, 680, 517, 32, 98, 105, 110]);
+    fn test_encode_num_zero() {
+        // a single digit 0 with its leading 1, not an empty segment
+        let mut codewords = [0u16; 3];
+        PDF417Encoder::new(&mut codewords, false).append_num(0);
+        assert_eq!(&codewords[1..], &[902, 10]);
+        assert!(roundtrip_with(|e| e.append_num(0), b"0", 0));
+        assert!(roundtrip_with(|e| e.append_int(0u8), b"0", 0));
     }
 
     #[test]
-    fn test_multiple_segments() {
+    fn test_encode_nums() {
+        let mut codewords = [0u16; 12];
+        let ec = PDF417Encoder::new(&mut codewords, false)
+            .append_nums([12, 3456, 0]);
+        assert_eq!(ec.used, codewords.len());
+        assert_eq!(&codewords, &[0, 902, 112, 900, 809, 902, 14, 856, 900, 809, 902, 10]);
+
+        assert!(roundtrip_with(|e| e.append_nums([12, 3456, 0, u64::MAX]), b"12 3456 0 18446744073709551615", 2));
+        assert!(roundtrip_with(|e| e.append_nums([]), b"", 2));
+    }
+
+    #[test]
+    fn test_encode_numeric_bytes() {
+        let digits = "0012345678987654321123456789876543211234567898765432100";
+        let mut expected = [0u16; 21];
+        let ec = PDF417Encoder::new(&mut expected, false).append_ascii(digits);
+        assert_eq!(ec.used, expected.len());
+
+        let mut codewords = [0u16; 21];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_numeric_bytes(digits.as_bytes());
+        assert_eq!(ec.used, codewords.len());
+        assert_eq!(codewords, expected);
+    }
+
+    #[test]
+    fn test_encode_numeric_bytes_leading_zeros() {
+        let mut codewords = [0u16; 4];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_numeric_bytes(b"0042");
+        assert_eq!(ec.used, codewords.len());
+        // 10042 = 11 * 900 + 142
+        assert_eq!(&codewords, &[0, 902, 11, 142]);
+    }
+
+    #[test]
+    fn test_encode_numeric_bytes_lengths() {
+        let mut digits = [b'9'; 88];
+        for len in 1..=88 {
+            let expected = 2 + (len / 44) * 15 + if len % 44 > 0 { len % 44 / 3 + 1 } else { 0 };
+            for pattern in [b'9', b'0', b'1'] {
+                digits[..len].fill(pattern);
+                digits[len - 1] = b'7';
+
+                let mut codewords = [0u16; 40];
+                let ec = PDF417Encoder::new(&mut codewords, false).append_numeric_bytes(&digits[..len]);
+                assert_eq!(ec.used, expected, "invalid codeword count for {len} digits");
+                assert!(codewords[2..expected].iter().all(|&cw| cw < 900));
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_gtin() {
+        use super::GtinError;
+        let mut expected = [0u16; 8];
+        PDF417Encoder::new(&mut expected, false).append_numeric_bytes(b"4006381333931");
+        let mut codewords = [0u16; 8];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_gtin("4006381333931").unwrap();
+        assert_eq!(ec.count(), 7);
+        assert_eq!(codewords, expected);
+
+        // UPC-A and GTIN-14 with leading zeros
+        let mut codewords = [0u16; 8];
+        assert!(PDF417Encoder::new(&mut codewords, false).append_gtin("036000291452").is_ok());
+        assert!(PDF417Encoder::new(&mut codewords, false).append_gtin("00012345600012").is_ok());
+
+        let ec = PDF417Encoder::new(&mut codewords, false);
+        assert_eq!(ec.append_gtin("4006381333932").err(), Some(GtinError::CheckDigit { expected: 1, actual: 2 }));
+        let ec = PDF417Encoder::new(&mut codewords, false);
+        assert_eq!(ec.append_gtin("400638133393").err(), Some(GtinError::CheckDigit { expected: 0, actual: 3 }));
+        let ec = PDF417Encoder::new(&mut codewords, false);
+        assert_eq!(ec.append_gtin("4006381").err(), Some(GtinError::InvalidLength(7)));
+        let ec = PDF417Encoder::new(&mut codewords, false);
+        assert_eq!(ec.append_gtin("40063813339a1").err(), Some(GtinError::InvalidDigit(11)));
+    }
+
+    #[test]
+    #[should_panic(expected = "numeric segments can only contain ASCII digits")]
+    fn test_encode_numeric_bytes_invalid() {
+        let mut codewords = [0u16; 4];
+        PDF417Encoder::new(&mut codewords, false).append_numeric_bytes(b"12a");
+    }
+
+    #[test]
+    #[allow(clippy::identity_op, clippy::erasing_op)]
+    fn test_generate_ascii_with_digits() {
+        let mut codewords = [0u16; 17];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_ascii("encoded 0123456789 as digits");
+        assert_eq!(ec.used, codewords.len());
+        assert_eq!(&codewords, &[0, 27 * 30 + 4, 13 * 30 + 2, 14 * 30 + 3, 4 * 30 + 3, 26 * 30 + 28, 0 * 30 + 1, 2 * 30 + 3, 4 * 30 + 5, 6 * 30 + 7, 8 * 30 + 9,
+            26 * 30 + 27, 0 * 30 + 18, 26 * 30 + 3, 8 * 30 + 6, 8 * 30 + 19, 18 * 30 + 29]);
+    }
+
+    #[test]
+    #[allow(clippy::identity_op, clippy::erasing_op)]
+    fn test_generate_ascii_punc_mixed() {
+        let mut codewords = [0u16; 18];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_ascii("This! Is a `quote (100%)`.");
+        assert_eq!(ec.used, codewords.len());
+        assert_eq!(&codewords, &[0, 19 * 30 + 27, 7 * 30 + 8, 18 * 30 + 29, 10 * 30 + 26, 27 * 30 + 8, 18 * 30 + 26, 0 * 30 + 26, 29 * 30 + 8, 16 * 30 + 20, 14 * 30 + 19, 4 * 30 + 26, 29 * 30 + 23, 28 * 30 + 1, 0 * 30 + 0, 21 * 30 + 25, 24 * 30 + 8, 17 * 30 + 29]);
+    }
+
+    #[test]
+    #[allow(clippy::identity_op)]
+    fn test_punctuation_threshold() {
+        let mut default = [0u16; 8];
+        let ec = PDF417Encoder::new(&mut default, false).append_ascii("a!?b");
+        // LL a PS ! PS ? b PS
+        assert_eq!(ec.as_codewords(), &[0, 27 * 30 + 0, 29 * 30 + 10, 29 * 30 + 25, 1 * 30 + 29]);
+
+        let mut codewords = [0u16; 8];
+        let ec = PDF417Encoder::new(&mut codewords, false)
+            .set_punctuation_threshold(2)
+            .append_ascii("a!?b");
+        // LL a ML PL ! ? AL LL b PS
+        assert_eq!(ec.as_codewords(), &[0, 27 * 30 + 0, 28 * 30 + 25, 10 * 30 + 25, 29 * 30 + 27, 1 * 30 + 29]);
+
+        let mut buf = [0u8; 4];
+        let len = super::decode(&ec.as_codewords()[1..], &mut buf).unwrap();
+        assert_eq!(&buf[..len], b"a!?b");
+    }
+
+    #[test]
+    #[allow(clippy::identity_op, clippy::erasing_op)]
+    fn test_numeric_threshold() {
+        let mut default = [0u16; 8];
+        let ec = PDF417Encoder::new(&mut default, false).append_ascii("1234567890");
+        // ML 1 2 3 4 5 6 7 8 9 0 PS
+        assert_eq!(ec.as_codewords(), &[0, 28 * 30 + 1, 2 * 30 + 3, 4 * 30 + 5, 6 * 30 + 7, 8 * 30 + 9, 0 * 30 + 29]);
+
+        let mut codewords = [0u16; 8];
+        let ec = PDF417Encoder::new(&mut codewords, false)
+            .set_numeric_threshold(8)
+            .append_ascii("1234567890");
+        assert_eq!(ec.as_codewords(), &[0, 902, 15, 369, 753, 190]);
+
+        let mut buf = [0u8; 10];
+        let len = super::decode(&ec.as_codewords()[1..], &mut buf).unwrap();
+        assert_eq!(&buf[..len], b"1234567890");
+    }
+
+    #[test]
+    #[allow(clippy::identity_op)]
+    fn test_new_raw() {
+        let mut codewords = [0u16; 12];
+        let ec = PDF417Encoder::new_raw(&mut codewords, false);
+        assert!(ec.is_empty());
+        let ec = ec.append_ascii("RAW");
+        assert_eq!(ec.as_codewords(), &[17 * 30 + 0, 22 * 30 + 29]);
+
+        let sealed = ec.seal(1);
+        // no length codeword, padded then followed by 4 ECC codewords
+        assert_eq!(&sealed[..8], &[17 * 30 + 0, 22 * 30 + 29, 900, 900, 900, 900, 900, 900]);
+        let mut expected = [0u16; 12];
+        expected[..8].copy_from_slice(&sealed[..8]);
+        crate::ecc::generate_ecc(&mut expected, 1);
+        assert_eq!(sealed, &expected);
+    }
+
+    #[test]
+    fn test_len_is_empty() {
+        for micro in [false, true] {
+            let mut codewords = [0u16; 8];
+            let ec = PDF417Encoder::new(&mut codewords, micro);
+            assert!(ec.is_empty());
+            assert_eq!(ec.len(), ec.count());
+            let ec = ec.append_ascii("A");
+            assert!(!ec.is_empty());
+            assert_eq!(ec.len(), ec.count());
+        }
+    }
+
+    #[test]
+    fn test_control_codewords() {
+        let mut codewords = [0u16; 16];
+        let ec = PDF417Encoder::new(&mut codewords, false)
+            .append_latch_numeric()
+            .append_num(5);
+        assert_eq!(ec.as_codewords(), &[0, 902, 15]);
+
+        let ec = PDF417Encoder::new(&mut codewords, false)
+            .append_latch_byte()
+            .append_raw(&[0x42])
+            .append_latch_text()
+            .append_ascii("AB")
+            .append_shift_byte(0xFF)
+            .append_ascii("CD");
+        assert_eq!(ec.as_codewords(), &[0, 901, 0x42, 900, 1, 913, 0xFF, 2 * 30 + 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the byte shift is only valid in the text compaction")]
+    fn test_shift_byte_not_text() {
+        let mut codewords = [0u16; 8];
+        PDF417Encoder::new(&mut codewords, false).append_latch_numeric().append_shift_byte(1);
+    }
+
+    #[test]
+    #[allow(clippy::identity_op)]
+    fn test_encode_ascii_byte_shift() {
+        // the text following a byte shift starts a new codeword, whatever
+        // the previous content of the storage
+        for fill in [0, 899] {
+            let mut codewords = [fill; 5];
+            PDF417Encoder::new(&mut codewords, false).append_ascii("A\x1bBC");
+            assert_eq!(&codewords[1..], &[29, 913, 27, 1 * 30 + 2]);
+
+            let mut codewords = [fill; 6];
+            PDF417Encoder::new(&mut codewords, false).append_ascii("ABC\x1bD");
+            assert_eq!(&codewords[1..], &[1, 2 * 30 + 29, 913, 27, 3 * 30 + 29]);
+        }
+
+        // the padding shift before 913 does not apply to the next character
+        let mut buf = [0u8; 8];
+        let len = super::decode(&[29, 913, 27, 1 * 30 + 2], &mut buf).unwrap();
+        assert_eq!(&buf[..len], b"A\x1bBC");
+    }
+
+    #[test]
+    fn test_append_plan() {
+        use super::Op::*;
+        let mut codewords = [0u16; 16];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_plan(&[
+            // "Hello" with a lower latch after the H, "+" after a mixed latch
+            Text(&[7, 27, 4, 11, 11, 14, 28, 20]),
+            LatchNumeric,
+            Digits(b"123"),
+            LatchText,
+            Text(&[26]),
+            ShiftByte(0xE9),
+            LatchByte,
+            Bytes(b"AB"),
+            Raw(&[900]),
+        ]);
+        assert_eq!(ec.as_codewords(), &[0, 237, 131, 344, 860, 902, 1, 223, 900, 809, 913, 0xE9, 901, 65, 66, 900]);
+        let mut buf = [0u8; 16];
+        let len = super::decode(&ec.as_codewords()[1..], &mut buf).unwrap();
+        assert_eq!(&buf[..len], b"Hello+123 \xE9AB");
+
+        let mut expected = [0u16; 16];
+        let n = PDF417Encoder::new(&mut expected, false).append_ascii("Hello").count();
+        let ec = PDF417Encoder::new(&mut codewords, false).append_plan(&[Text(&[7, 27, 4, 11, 11, 14])]);
+        assert_eq!(ec.as_codewords(), &expected[..n]);
+
+        // the text continues in the sub-mode the plan ends in
+        let mut expected = [0u16; 16];
+        let n = PDF417Encoder::new(&mut expected, false).append_ascii("Hello").append_ascii("A!").count();
+        let ec = ec.append_ascii("A!");
+        assert_eq!(ec.as_codewords(), &expected[..n]);
+
+        // in place of the padding value
+        let n = PDF417Encoder::new(&mut expected, false).append_ascii("H").append_ascii("i").count();
+        let ec = PDF417Encoder::new(&mut codewords, false).append_plan(&[Text(&[7])]).append_ascii("i");
+        assert_eq!(ec.as_codewords(), &expected[..n]);
+
+        // the byte shift is valid after a text operation
+        let ec = PDF417Encoder::new(&mut codewords, false).append_plan(&[Text(&[7, 27, 8])]).append_shift_byte(0xE9);
+        let len = super::decode(&ec.as_codewords()[1..], &mut buf).unwrap();
+        assert_eq!(&buf[..len], b"Hi\xE9");
+    }
+
+    #[test]
+    fn test_available() {
+        let mut codewords = [0u16; 12];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_raw(&[900; 2]);
+        assert_eq!(ec.available_regular(2), Some(1));
+        assert_eq!(ec.available_regular(2), Some(ec.available(2)));
+        let ec = ec.append_codeword(900);
+        assert_eq!(ec.available_regular(2), Some(0));
+        let ec = ec.append_codeword(900);
+        assert_eq!(ec.available_regular(2), None);
+        assert_eq!(ec.available_regular(1), Some(3));
+        assert_eq!(ec.available_regular(3), None);
+
+        // variant 0 (11x1) holds 4 data codewords
+        let mut codewords = [0u16; 11];
+        let ec = PDF417Encoder::new(&mut codewords, true).append_raw(&[900; 4]);
+        assert_eq!(ec.available_micro(0), Some(0));
+        assert_eq!(ec.available_micro(1), Some(3));
+        let ec = ec.append_codeword(900);
+        assert_eq!(ec.available_micro(0), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "the data and the ECC codewords exceed the capacity")]
+    fn test_available_overflow() {
+        let mut codewords = [0u16; 12];
+        PDF417Encoder::new(&mut codewords, false).append_raw(&[900; 4]).available(2);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid variant (0-33)")]
+    fn test_available_invalid_variant() {
+        let mut codewords = [0u16; 12];
+        PDF417Encoder::new(&mut codewords, true).available(34);
+    }
+
+    #[test]
+    fn test_data_capacity() {
+        let mut codewords = [0u16; 30];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_ascii("Data");
+        assert_eq!(ec.capacity(), 30);
+        // 8 ECC codewords for the level 2
+        assert_eq!(ec.data_capacity(2), 22);
+        assert_eq!(ec.data_capacity(2), ec.count() + ec.available(2));
+        assert_eq!(ec.data_capacity(4), 0);
+    }
+
+    #[test]
+    fn test_utilization() {
+        // 20 codewords, 8 of them for the ECC level 2 and 1 for the length
+        let mut codewords = [0u16; 20];
+        let ec = PDF417Encoder::new(&mut codewords, false);
+        assert_eq!(ec.utilization(2), Some(0.0));
+
+        let ec = ec.append_bytes(b"fill it up");
+        assert_eq!(ec.count(), 11);
+        assert_eq!(ec.utilization(2), Some(10.0 / 11.0));
+        assert!(ec.utilization(3).unwrap() > 1.0);
+        // 32 ECC codewords for the level 4
+        assert_eq!(ec.utilization(4), None);
+
+        let mut codewords = [0u16; 20];
+        let ec = PDF417Encoder::new_raw(&mut codewords, false).append_bytes(b"fill it up");
+        assert_eq!(ec.utilization(2), Some(10.0 / 12.0));
+
+        let mut codewords = [0u16; 16];
+        let ec = PDF417Encoder::new(&mut codewords, true);
+        assert_eq!(ec.utilization(0), Some(0.0));
+    }
+
+    #[test]
+    fn test_transition_cost() {
+        use super::{transition_cost, Mode::*};
+
+        // number of codewords (length slot excluded) for a number of values
+        let count = |f: fn(PDF417Encoder) -> PDF417Encoder| {
+            let mut codewords = [0u16; 16];
+            f(PDF417Encoder::new(&mut codewords, false)).count() - 1
+        };
+        let values = |n: u8| (n as usize + 1) / 2;
+
+        assert_eq!(count(|e| e.append_ascii("Abc")), values(1 + transition_cost(Upper, Lower) + 2));
+        assert_eq!(count(|e| e.append_ascii("A12")), values(1 + transition_cost(Upper, Mixed) + 2));
+        assert_eq!(count(|e| e.append_ascii("A;<>")), values(1 + transition_cost(Upper, Punctuation) + 3));
+        assert_eq!(count(|e| e.append_ascii("a1Bc")),
+            values(transition_cost(Upper, Lower) + 1 + transition_cost(Lower, Mixed) + 1 + transition_cost(Mixed, Upper) + 1 + transition_cost(Upper, Lower) + 1));
+        assert_eq!(count(|e| e.append_ascii(";<>aa")),
+            values(transition_cost(Upper, Punctuation) + 3 + transition_cost(Punctuation, Lower) + 2));
+        assert_eq!(count(|e| e.append_ascii("ab;<>A")),
+            values(transition_cost(Upper, Lower) + 2 + transition_cost(Lower, Punctuation) + 3 + transition_cost(Punctuation, Upper) + 1));
+        // numeric -> text (upper)
+        assert_eq!(count(|e| e.append_num(1).append_ascii("AB")), 2 + values(transition_cost(Numeric, Upper) + 2));
+        assert_eq!(count(|e| e.append_num(1).append_ascii("ab")), 2 + values(transition_cost(Numeric, Lower) + 2));
+    }
+
+    #[test]
+    fn test_append_numeric_raw() {
+        let mut cached = [0u16; 8];
+        let ec = PDF417Encoder::new(&mut cached, false).append_num(1234567890123);
+        let field = &ec.as_codewords()[2..];
+
+        let mut expected = [0u16; 16];
+        let expected = PDF417Encoder::new(&mut expected, false)
+            .append_ascii("ID").append_num(1234567890123).append_num(42);
+        let mut codewords = [0u16; 16];
+        let ec = PDF417Encoder::new(&mut codewords, false)
+            .append_ascii("ID").append_numeric_raw(field).append_num(42);
+        assert_eq!(ec.as_codewords(), expected.as_codewords());
+    }
+
+    #[test]
+    #[should_panic(expected = "numeric codewords must be less than 900")]
+    fn test_append_numeric_raw_invalid() {
+        let mut codewords = [0u16; 8];
+        PDF417Encoder::new(&mut codewords, false).append_numeric_raw(&[1, 900]);
+    }
+
+    #[test]
+    fn test_append_segment_payload() {
+        let mut payload = [0u16; 8];
+        let segment = PDF417Encoder::new(&mut payload, false).append_ascii("Seg").append_num(42);
+        let payload = &segment.as_codewords()[1..];
+
+        let mut codewords = [0u16; 16];
+        let sealed = PDF417Encoder::new(&mut codewords, false)
+            .append_segment_payload(payload)
+            .seal(2);
+        assert_eq!(&sealed[1..=payload.len()], payload);
+
+        assert!(roundtrip_with(|e| e.append_segment_payload(payload).append_ascii("!"), b"Seg42!", 2));
+        // a text latch is inserted after a numeric segment
+        assert!(roundtrip_with(|e| e.append_num(7).append_segment_payload(payload), b"7Seg42", 2));
+    }
+
+    #[test]
+    #[should_panic(expected = "the payload must only contain data codewords")]
+    fn test_append_segment_payload_macro() {
+        let mut codewords = [0u16; 8];
+        PDF417Encoder::new(&mut codewords, false).append_segment_payload(&[1, 928, 2]);
+    }
+
+    #[test]
+    fn test_encode_bits() {
+        let bits = [true, false, true, true, false, false, true, false, true, true, true, true];
+        let mut codewords = [0u16; 4];
+        PDF417Encoder::new(&mut codewords, false).append_bits(&bits);
+        assert_eq!(&codewords, &[0, 901, 0b1011_0010, 0b1111_0000]);
+
+        let mut codewords = [0u16; 3];
+        PDF417Encoder::new(&mut codewords, false).append_bits(&bits[..3]);
+        assert_eq!(&codewords, &[0, 913, 0b1010_0000]);
+    }
+
+    #[test]
+    #[cfg(feature = "cp437")]
+    fn test_encode_cp437() {
+        use super::cp437_byte;
+        let mut codewords = [0u16; 11];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_cp437("┌─┐│└┘░█");
+        assert_eq!(ec.used, codewords.len());
+        // 8 bytes: a group of 6 bytes (5 codewords) + 2 single bytes
+        assert_eq!(&codewords[..4], &[0, 927, 2, 901]);
+        assert_eq!(&codewords[9..], &[0xB0, 0xDB]);
+
+        let mut bytes = [0u8; 6];
+        for (b, c) in bytes.iter_mut().zip("┌─┐│└┘".chars()) {
+            *b = cp437_byte(c).unwrap();
+        }
+        assert_eq!(bytes, [0xDA, 0xC4, 0xBF, 0xB3, 0xC0, 0xD9]);
+        assert_eq!(cp437_byte('A'), Some(b'A'));
+        assert_eq!(cp437_byte('é'), Some(0x82));
+        assert_eq!(cp437_byte('\u{A0}'), Some(0xFF));
+        assert_eq!(cp437_byte('€'), None);
+    }
+
+    #[test]
+    #[cfg(feature = "cp437")]
+    #[should_panic(expected = "is not part of the code page 437")]
+    fn test_encode_cp437_unmappable() {
+        let mut codewords = [0u16; 10];
+        PDF417Encoder::new(&mut codewords, false).append_cp437("10 €");
+    }
+
+    #[test]
+    #[cfg(feature = "cp437")]
+    fn test_try_encode_cp437() {
+        use super::Cp437Error;
+
+        let mut codewords = [0u16; 10];
+        let err = PDF417Encoder::new(&mut codewords, false).try_append_cp437("10 €").unwrap_err();
+        assert_eq!(err, Cp437Error { index: 3, character: '€' });
+
+        let mut expected = [0u16; 10];
+        let ec = PDF417Encoder::new(&mut codewords, false).try_append_cp437("é").unwrap();
+        let ex = PDF417Encoder::new(&mut expected, false).append_cp437("é");
+        assert_eq!(ec.as_codewords(), ex.as_codewords());
+    }
+
+    #[test]
+    fn test_encode_eci_custom() {
+        let mut codewords = [0u16; 7];
+        let ec = PDF417Encoder::new(&mut codewords, false)
+            .append_eci_custom(810900)
+            .append_eci_custom(811234)
+            .append_eci_custom(811799);
+        assert_eq!(ec.used, codewords.len());
+        assert_eq!(&codewords, &[0, 925, 0, 925, 334, 925, 899]);
+    }
+
+    #[test]
+    #[should_panic(expected = "user defined ECI identifiers must be between 810900 and 811799")]
+    fn test_encode_eci_custom_invalid() {
+        let mut codewords = [0u16; 3];
+        PDF417Encoder::new(&mut codewords, false).append_eci_custom(811800);
+    }
+
+    #[test]
+    #[allow(clippy::identity_op, clippy::erasing_op)]
+    fn test_encode_eci_then_ascii() {
+        let mut codewords = [0u16; 6];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_eci_then_ascii(3, "ABC");
+        assert_eq!(ec.used, codewords.len());
+        // ECI 3, TL, AB C PS
+        assert_eq!(&codewords, &[0, 927, 3, 900, 0 * 30 + 1, 2 * 30 + 29]);
+
+        let mut codewords = [0u16; 5];
+        let ec = PDF417Encoder::new(&mut codewords, true).append_eci_then_ascii(1000, "AB");
+        assert_eq!(ec.used, codewords.len());
+        assert_eq!(&codewords, &[926, 0, 100, 900, 0 * 30 + 1]);
+
+        let mut buf = [0u8; 2];
+        let len = super::decode(&codewords, &mut buf).unwrap();
+        assert_eq!(&buf[..len], b"AB");
+    }
+
+    #[test]
+    #[should_panic(expected = "ECI identifiers must be at most 811799")]
+    fn test_encode_eci_then_ascii_invalid() {
+        let mut codewords = [0u16; 8];
+        PDF417Encoder::new(&mut codewords, false).append_eci_then_ascii(811800, "A");
+    }
+
+    #[test]
+    fn test_encode_bytes_multiple() {
+        let mut codewords = [0u16; 7];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_bytes(b"alcool");
+        assert_eq!(ec.used, codewords.len());
+        assert_eq!(&codewords, &[0, 924, 163, 238, 432, 766, 244]);
+    }
+
+    #[test]
+    fn test_encode_bytes_with_count() {
+        // 11 bytes: a packed group of 6 bytes then 5 single bytes
+        let mut codewords = [0u16; 14];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_bytes_with_count(b"encode bins");
+        assert_eq!(ec.used, codewords.len());
+        assert_eq!(&codewords, &[0, 902, 111, 901, 169, 883, 224, 680, 517, 32, 98, 105, 110, 115]);
+        assert!(roundtrip_with(|e| e.append_bytes_with_count(b"encode bins"), b"11encode bins", 2));
+    }
+
+    #[test]
+    fn test_encode_length_prefixed() {
+        let mut codewords = [0u16; 7];
+        PDF417Encoder::new(&mut codewords, false).append_length_prefixed(&[1, 2, 3]);
+        assert_eq!(&codewords, &[0, 901, 0, 3, 1, 2, 3]);
+        assert!(roundtrip_with(|e| e.append_length_prefixed(&[1, 2, 3]), &[0, 3, 1, 2, 3], 2));
+        let data = [0xAB; 300];
+        let mut expected = [0xAB; 302];
+        expected[..2].copy_from_slice(&[1, 44]);
+        assert!(roundtrip_with(|e| e.append_length_prefixed(&data), &expected, 2));
+    }
+
+    #[test]
+    fn test_crc16_ccitt() {
+        use super::crc16_ccitt;
+        // check value of the CRC-16/CCITT-FALSE catalog entry
+        assert_eq!(crc16_ccitt(b"123456789"), 0x29B1);
+        assert_eq!(crc16_ccitt(b""), 0xFFFF);
+        assert_eq!(crc16_ccitt(b"A"), 0xB915);
+    }
+
+    #[test]
+    fn test_encode_with_crc16() {
+        assert!(roundtrip_with(|e| e.append_with_crc16(b"123456789"), b"123456789\x29\xB1", 2));
+
+        let mut codewords = [0u16; 5];
+        PDF417Encoder::new(&mut codewords, false).append_with_crc16(b"A");
+        assert_eq!(&codewords, &[0, 901, 0x41, 0xB9, 0x15]);
+    }
+
+    #[test]
+    fn test_encode_bytes_not_multiple() {
+        let mut codewords = [0u16; 11];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_bytes(b"encode bin");
+        assert_eq!(ec.used, codewords.len());
+        assert_eq!(&codewords, &[0, 901, 169, 883, 224, 680, 517, 32, 98, 105, 110]);
+    }
+
+    #[test]
+    fn test_is_text_encodable() {
+        use super::{is_text_encodable, MIXED_CHAR_SET, PUNC_CHAR_SET, M_SHIFT_BYTE};
+        for c in 0..=255u8 {
+            let expected = c.is_ascii_uppercase() || c.is_ascii_lowercase() || c.is_ascii_digit()
+                || c == b' ' || MIXED_CHAR_SET.contains(&c) || PUNC_CHAR_SET.contains(&c);
+            assert_eq!(is_text_encodable(c), expected, "{c}");
+
+            if c.is_ascii() {
+                let mut codewords = [0u16; 4];
+                let s = [c];
+                let ec = PDF417Encoder::new(&mut codewords, false)
+                    .append_ascii(core::str::from_utf8(&s).unwrap());
+                assert_eq!(ec.as_codewords().contains(&M_SHIFT_BYTE), !expected, "{c}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_append_raw_range() {
+        use std::string::ToString;
+        use super::CodewordError;
+        let mut codewords = [0u16; 8];
+        let ec = PDF417Encoder::new(&mut codewords, false).try_append_raw(&[1, 928, 900]).unwrap();
+        let ec = ec.try_append_codeword(928).unwrap();
+        assert_eq!(ec.count(), 5);
+
+        let err = ec.try_append_raw(&[10, 929]).err().unwrap();
+        assert_eq!(err, CodewordError { index: 1, codeword: 929 });
+        assert_eq!(err.to_string(), "invalid codeword 929 at index 1 (must be less than 929)");
+        assert_eq!(codewords[..5], [0, 1, 928, 900, 928]);
+        assert_eq!(codewords[5], 0);
+
+        let ec = PDF417Encoder::new(&mut codewords, false);
+        assert!(ec.try_append_codeword(u16::MAX).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid codeword 929")]
+    fn test_append_codeword_out_of_range() {
+        let mut codewords = [0u16; 4];
+        PDF417Encoder::new(&mut codewords, false).append_codeword(929);
+    }
+
+    #[test]
+    fn test_byte_segment_codewords() {
+        let bytes = [0xA5u8; 12];
+        for len in [0, 1, 2, 5, 6, 7, 11, 12] {
+            let mut codewords = [0u16; 16];
+            let ec = PDF417Encoder::new(&mut codewords, false).append_bytes(&bytes[..len]);
+            assert_eq!(ec.used - 1, super::byte_segment_codewords(len), "invalid count for {len} bytes");
+        }
+    }
+
+    #[test]
+    fn test_multiple_segments() {
         let mut codewords = [0u16; 1 + 3 + 2 + 10];
 
         let ec = PDF417Encoder::new(&mut codewords, false)
@@ -580,6 +2911,441 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_snapshot_restore() {
+        let mut expected = [0u16; 8];
+        PDF417Encoder::new(&mut expected, false)
+            .append_ascii("Test")
+            .append_num(42);
+
+        let mut codewords = [0u16; 32];
+        let mut ec = PDF417Encoder::new(&mut codewords, false).append_ascii("Test");
+        let state = ec.snapshot();
+        let prefix = ec.count();
+
+        ec = ec.append_bytes(b"encode bin").append_ascii("more");
+        assert!(ec.count() > prefix);
+        ec.restore(state);
+        assert_eq!(ec.snapshot(), state);
+        assert_eq!(ec.as_codewords(), &expected[..prefix]);
+
+        let ec = ec.append_num(42);
+        assert_eq!(ec.as_codewords(), &expected[..6]);
+    }
+
+    #[test]
+    fn test_array_encoder() {
+        use super::PDF417ArrayEncoder;
+        let mut expected = [0u16; 18];
+        PDF417Encoder::new(&mut expected, false)
+            .append_ascii("Test")
+            .append_num(42)
+            .append_bytes(b"encode bin")
+            .seal(0);
+
+        let encoder = PDF417ArrayEncoder::<18>::new(false)
+            .append(|e| e.append_ascii("Test").append_num(42))
+            .append(|e| e.append_bytes(b"encode bin"));
+        assert_eq!(encoder.count(), 16);
+        assert_eq!(encoder.seal::<0>(), expected);
+    }
+
+    #[test]
+    fn test_batch_encoder() {
+        use super::BatchEncoder;
+        let mut arena = [0u16; 40];
+        let mut batch = BatchEncoder::new(&mut arena, false);
+        let first = batch.encode(3 * 4, 1, |e| e.append_ascii("First")).unwrap();
+        let second = batch.encode(4 * 5, 2, |e| e.append_num(1234567890)).unwrap();
+        assert_eq!((first, second), ((0, 12), (12, 20)));
+        assert_eq!(batch.remaining(), 8);
+        // too much data, the failed symbol does not use the storage
+        assert!(batch.encode(8, 2, |e| e.append_ascii("Too long")).is_err());
+        assert_eq!(batch.count(), 32);
+
+        let mut expected = [0u16; 12];
+        PDF417Encoder::new(&mut expected, false).append_ascii("First").seal(1);
+        assert_eq!(arena[..12], expected);
+        let mut expected = [0u16; 20];
+        PDF417Encoder::new(&mut expected, false).append_num(1234567890).seal(2);
+        assert_eq!(arena[12..32], expected);
+
+        let a = crate::PDF417::new(&arena[first.0..first.0 + first.1], 3, 4, 1);
+        let b = crate::PDF417::new(&arena[second.0..second.0 + second.1], 4, 5, 2);
+        assert!(!a.is_effectively_empty() && !b.is_effectively_empty());
+    }
+
+    #[test]
+    fn test_sink_encoder() {
+        use super::{CodewordSink, PDF417SinkEncoder, SinkFullError};
+
+        struct Collector {
+            codewords: [u16; 32],
+            len: usize,
+        }
+
+        impl CodewordSink for Collector {
+            type Error = ();
+
+            fn push(&mut self, cw: u16) -> Result<(), ()> {
+                *self.codewords.get_mut(self.len).ok_or(())? = cw;
+                self.len += 1;
+                Ok(())
+            }
+        }
+
+        for micro in [false, true] {
+            let mut storage = [0u16; 32];
+            let ec = PDF417Encoder::new(&mut storage, micro)
+                .append_ascii("Hello")
+                .append_ascii("$")
+                .append_num(1234567890)
+                .append_bytes(b"\xFFbytes");
+            let offset = if micro { 0 } else { 1 };
+            let expected = &ec.as_codewords()[offset..];
+
+            let sink = Collector { codewords: [0; 32], len: 0 };
+            let sink = PDF417SinkEncoder::<_, 16>::new(sink, micro)
+                .append(|e| e.append_ascii("Hello")).unwrap()
+                .append(|e| e.append_ascii("$")).unwrap()
+                .append(|e| e.append_num(1234567890)).unwrap()
+                .append(|e| e.append_bytes(b"\xFFbytes")).unwrap()
+                .finish().unwrap();
+            assert_eq!(&sink.codewords[..sink.len], expected);
+        }
+
+        let mut codewords = [0u16; 2];
+        let result = PDF417SinkEncoder::<_, 8>::new(&mut codewords[..], false)
+            .append(|e| e.append_ascii("Hello"));
+        assert_eq!(result.err(), Some(SinkFullError));
+    }
+
+    /// Encodes the data segments, seals them with the ECC level `level` and
+    /// checks that decoding the data codewords gives back `expected`.
+    fn roundtrip_with<F>(segments: F, expected: &[u8], level: u8) -> bool
+    where F: for<'b> FnOnce(PDF417Encoder<'b>) -> PDF417Encoder<'b> {
+        let mut codewords = [0u16; 928];
+        let sealed = segments(PDF417Encoder::new(&mut codewords, false)).seal(level);
+        let data = &sealed[1..sealed[0] as usize];
+
+        let mut buf = [0u8; 1024];
+        match super::decode(data, &mut buf) {
+            Ok(len) => &buf[..len] == expected,
+            Err(_) => false,
+        }
+    }
+
+    /// Roundtrip of a string encoded with the ASCII mode (or the UTF-8 mode
+    /// if it is not ASCII).
+    fn roundtrip(text: &str, level: u8) -> bool {
+        if text.is_ascii() {
+            roundtrip_with(|e| e.append_ascii(text), text.as_bytes(), level)
+        } else {
+            roundtrip_with(|e| e.append_utf8(text), text.as_bytes(), level)
+        }
+    }
+
+    #[test]
+    fn test_best_dimensions() {
+        assert_eq!(super::best_dimensions(1), Some((3, 1)));
+        assert_eq!(super::best_dimensions(90), Some((3, 30)));
+        assert_eq!(super::best_dimensions(91), Some((7, 13)));
+        assert_eq!(super::best_dimensions(928), Some((32, 29)));
+        assert_eq!(super::best_dimensions(929), None);
+    }
+
+    #[test]
+    fn test_encode_ascii_array() {
+        use super::encode_ascii;
+        let mut expected = [0u16; 5 * 3];
+        PDF417Encoder::new(&mut expected, false).append_ascii("Hello").seal(2);
+        assert_eq!(encode_ascii::<{ 5 * 3 }>("Hello", 2), Some(expected));
+
+        // 4 data codewords and 8 ECC codewords
+        assert!(encode_ascii::<12>("Hello", 2).is_some());
+        assert_eq!(encode_ascii::<11>("Hello", 2), None);
+        // the data fits but not the ECC codewords
+        assert_eq!(encode_ascii::<6>("Hello", 2), None);
+        assert_eq!(encode_ascii::<0>("", 0), None);
+        // exact fits, the byte shift and the numeric compaction included
+        for text in ["Hello", "a\x1bb", "Z\x1b", "12345678901234567890"] {
+            let mut codewords = [0u16; 64];
+            let count = PDF417Encoder::new(&mut codewords, false).append_ascii(text).count();
+            assert!(PDF417Encoder::new(&mut codewords[..count], false).try_append_text(text.as_bytes()).is_some());
+            assert!(PDF417Encoder::new(&mut codewords[..count - 1], false).try_append_text(text.as_bytes()).is_none());
+        }
+
+        // 1500 digits fit in 928 codewords using the numeric compaction
+        let digits = [b'7'; 1500];
+        let digits = core::str::from_utf8(&digits).unwrap();
+        assert!(encode_ascii::<928>(digits, 2).is_some());
+    }
+
+    #[test]
+    fn test_set_length_codeword() {
+        for level in 0..4 {
+            let mut sealed = [0u16; 6 * 5];
+            PDF417Encoder::new(&mut sealed, false).append_ascii("Length").seal(level);
+
+            let mut storage = [0u16; 6 * 5];
+            super::set_length_codeword(&mut storage, 6, 5, level);
+            assert_eq!(storage[0], sealed[0]);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "16 ECC codewords do not fit in a symbol of 12 codewords")]
+    fn test_set_length_codeword_ecc_overflow() {
+        let mut storage = [0u16; 4 * 3];
+        super::set_length_codeword(&mut storage, 4, 3, 3);
+    }
+
+    #[test]
+    fn test_max_level_for_dimensions() {
+        use super::max_level_for_dimensions;
+        // 24 codewords: 8 data + 16 ECC codewords at most (levels 0 to 3)
+        assert_eq!(max_level_for_dimensions(8, 3, 8), Some(3));
+        assert_eq!(max_level_for_dimensions(9, 3, 8), Some(2));
+        assert_eq!(max_level_for_dimensions(23, 3, 8), None);
+        assert_eq!(max_level_for_dimensions(1, 30, 30), Some(8));
+        assert_eq!(max_level_for_dimensions(1, 2, 8), None);
+        assert_eq!(max_level_for_dimensions(1, 3, 31), None);
+        assert_eq!(max_level_for_dimensions(1, 90, 30), None);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_auto_render() {
+        use crate::{PDF417, MicroPDF417, PDF417Row};
+        let mut input = [0u16; 928];
+        for len in [1, 10, 100, 150] {
+            let text = "A1b2;".repeat(len);
+            let encoder = PDF417Encoder::new(&mut input, false).append_ascii(&text);
+            let count = encoder.count();
+            let (codewords, (rows, cols), level) = encoder.auto_render().unwrap();
+            assert_eq!(codewords.len(), rows as usize * cols as usize);
+            assert!(count + crate::ecc::ecc_count(level) <= codewords.len());
+            assert_eq!(codewords[0] as usize, codewords.len() - crate::ecc::ecc_count(level));
+            let pdf417 = PDF417::new(&codewords, rows, cols, level);
+            assert_eq!(pdf417.iter().map(|row: PDF417Row| row.len()).sum::<usize>(), rows as usize * (cols as usize + 4));
+        }
+
+        let mut input = [0u16; 176];
+        let (codewords, (rows, cols), variant) = PDF417Encoder::new(&mut input, true)
+            .append_ascii("Hello, world!").auto_render().unwrap();
+        let variant = crate::Variant::from(variant);
+        assert_eq!((rows, cols), (variant.rows(), variant.cols()));
+        assert_eq!(codewords.len(), rows as usize * cols as usize);
+        MicroPDF417::from_variant(&codewords, variant);
+
+        let mut input = [0u16; 1200];
+        assert!(PDF417Encoder::new(&mut input, false).append_bytes(&[0xAA; 1080]).auto_render().is_none());
+    }
+
+    #[test]
+    #[allow(clippy::identity_op)]
+    fn test_chained_segment_boundaries() {
+        // the mixed submode of "123" is kept: a single latch to lower
+        let mut codewords = [0u16; 5];
+        PDF417Encoder::new(&mut codewords, false).append_ascii("123").append_ascii("abc");
+        assert_eq!(&codewords[1..], &[28 * 30 + 1, 2 * 30 + 3, 27 * 30, 1 * 30 + 2]);
+
+        // numeric segment then text: a single text latch
+        let mut codewords = [0u16; 6];
+        PDF417Encoder::new(&mut codewords, false).append_num(42).append_ascii("abc");
+        assert_eq!(&codewords[1..], &[902, 142, 900, 27 * 30, 1 * 30 + 2]);
+
+        // numeric run at the end of a string then text: a single text latch
+        let mut codewords = [0u16; 10];
+        let mut numeric = [0u16; 7];
+        PDF417Encoder::new(&mut numeric, false).append_numeric_bytes(b"12345678901234");
+        PDF417Encoder::new(&mut codewords, false).append_ascii("12345678901234").append_ascii("abc");
+        assert_eq!(codewords[1..7], numeric[1..]);
+        assert_eq!(&codewords[7..], &[900, 27 * 30, 1 * 30 + 2]);
+    }
+
+    #[test]
+    fn test_chained_ascii() {
+        for (a, b) in [("123", "abc"), ("12", "abc"), ("abc", "DEF"), ("a", "1"), ("x!", "?y"), ("12345678901234", "abc"), ("A.", "b")] {
+            let mut chained = [0u16; 32];
+            let mut single = [0u16; 32];
+            let n = PDF417Encoder::new(&mut chained, false).append_ascii(a).append_ascii(b).count();
+            let mut joined = [0u8; 32];
+            joined[..a.len()].copy_from_slice(a.as_bytes());
+            joined[a.len()..a.len() + b.len()].copy_from_slice(b.as_bytes());
+            let joined = core::str::from_utf8(&joined[..a.len() + b.len()]).unwrap();
+            let m = PDF417Encoder::new(&mut single, false).append_ascii(joined).count();
+            assert_eq!(chained[..n], single[..m], "{a:?} + {b:?}");
+
+            assert!(roundtrip_with(|e| e.append_ascii(a).append_ascii(b), joined.as_bytes(), 0));
+        }
+    }
+
+    #[test]
+    fn test_encode_text_smart() {
+        let text = "Hello, world! This is a mostly ASCII string with a single emoji \u{1F600} in it.";
+        let mut codewords = [0u16; 128];
+        let smart = PDF417Encoder::new(&mut codewords, false).append_text_smart(text).count();
+        let mut codewords = [0u16; 128];
+        let utf8 = PDF417Encoder::new(&mut codewords, false).append_utf8(text).count();
+        assert!(smart < utf8, "{smart} >= {utf8}");
+
+        for level in [0, 2] {
+            for text in [text, "\u{e9}t\u{e9} \u{e0} Paris", "\u{1F600}", "only ascii", "\u{e9}\u{e9} short \u{e8}\u{e8}"] {
+                assert!(roundtrip_with(|e| e.append_text_smart(text), text.as_bytes(), level), "{text}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        use super::{decode, DecodeError};
+        let mut buf = [0u8; 64];
+
+        // micro symbols have no length codeword
+        let mut codewords = [0u16; 20];
+        let ec = PDF417Encoder::new(&mut codewords, true)
+            .append_utf8("h\u{e9}")
+            .append_ascii("Test 1234")
+            .append_bytes(&[0, 255, 1]);
+        let len = decode(ec.as_codewords(), &mut buf).unwrap();
+        assert_eq!(&buf[..len], b"h\xC3\xA9Test 1234\x00\xFF\x01");
+
+        assert_eq!(decode(&[1, 2], &mut buf[..3]), Err(DecodeError::BufferTooSmall));
+        assert_eq!(decode(&[1, 929], &mut buf), Err(DecodeError::InvalidCodeword { index: 1, codeword: 929 }));
+    }
+
+    #[test]
+    fn test_decode_to_str() {
+        use super::decode_to_str;
+        let mut buf = [0u8; 64];
+
+        let mut codewords = [0u16; 32];
+        let ec = PDF417Encoder::new(&mut codewords, false)
+            .append_ascii("Order #")
+            .append_num(12345678901234567)
+            .append_ascii(" shipped")
+            .append_utf8(" \u{2713}");
+        let text = decode_to_str(&ec.as_codewords()[1..], &mut buf).unwrap();
+        assert_eq!(text, "Order #12345678901234567 shipped \u{2713}");
+
+        let mut codewords = [0u16; 16];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_bytes(b"a\xFF\xE2\x9Cb\xE2");
+        assert_eq!(decode_to_str(&ec.as_codewords()[1..], &mut buf).unwrap(), "a???b?");
+    }
+
+    #[test]
+    fn test_encode_record() {
+        let mut expected = [0u16; 16];
+        let n = PDF417Encoder::new(&mut expected, false).append_ascii("ABC,123,x,y").count();
+        let mut codewords = [0u16; 16];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_record(&["ABC", "123", "x,y"], ',');
+        assert_eq!(ec.as_codewords(), &expected[..n]);
+
+        // the comma is latched in mixed mode before the digit instead of
+        // being shifted as if it was isolated
+        let mut record = [0u16; 8];
+        let n = PDF417Encoder::new(&mut record, false).append_record(&["A", "1"], ',').count();
+        let mut isolated = [0u16; 8];
+        let m = PDF417Encoder::new(&mut isolated, false).append_ascii("A").append_ascii(",").append_ascii("1").count();
+        assert_eq!((n, m), (3, 4));
+
+        // the punctuation and mixed characters around the separators are
+        // encoded as in the joined text
+        for (fields, sep, joined) in [
+            (&["A\t", "B"][..], ',', "A\t,B"),
+            (&["a", "", "", "b"][..], ';', "a;;;b"),
+            (&["#", "@x"][..], ';', "#;@x"),
+            (&["12", "34", "5"][..], '.', "12.34.5"),
+            (&["x:", "", "9"][..], '-', "x:--9"),
+        ] {
+            let mut expected = [0u16; 16];
+            let n = PDF417Encoder::new(&mut expected, false).append_ascii(joined).count();
+            let mut codewords = [0u16; 16];
+            let ec = PDF417Encoder::new(&mut codewords, false).append_record(fields, sep);
+            assert_eq!(ec.as_codewords(), &expected[..n], "{joined:?}");
+        }
+
+        assert!(roundtrip_with(|e| e.append_record(&["ABC", "123", "x,y"], ','), b"ABC,123,x,y", 0));
+        assert!(roundtrip_with(|e| e.append_record(&["12.50", "EUR", "", "total"], ';'), b"12.50;EUR;;total", 0));
+    }
+
+    #[test]
+    fn test_encode_timestamp() {
+        let mut codewords = [0u16; 8];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_timestamp(1_700_000_000);
+        // T S PS :, then 1700000000 in the numeric compaction (902)
+        assert_eq!(ec.as_codewords(), &[0, 19 * 30 + 18, 29 * 30 + 14, 902, 16, 44, 400, 0]);
+        assert!(roundtrip_with(|e| e.append_timestamp(1_700_000_000), b"TS:1700000000", 2));
+    }
+
+    #[test]
+    fn test_encode_coordinate() {
+        for ((lat, lon), expected) in [
+            ((48_858_370, 2_294_481), b"+48858370,+002294481"),
+            ((-33_856_784, 151_215_297), b"-33856784,+151215297"),
+            ((0, -180_000_000), b"+00000000,-180000000"),
+        ] {
+            let mut codewords = [0u16; 16];
+            let ec = PDF417Encoder::new(&mut codewords, false).append_coordinate(lat, lon);
+            let mut buf = [0u8; 20];
+            let len = super::decode(&ec.as_codewords()[1..], &mut buf).unwrap();
+            assert_eq!(&buf[..len], expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "the latitude must be between -90 and 90 degrees")]
+    fn test_encode_coordinate_invalid() {
+        let mut codewords = [0u16; 16];
+        PDF417Encoder::new(&mut codewords, false).append_coordinate(90_000_001, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "the separator must be encodable in text mode")]
+    fn test_encode_record_invalid_separator() {
+        let mut codewords = [0u16; 16];
+        PDF417Encoder::new(&mut codewords, false).append_record(&["a", "b"], '\u{7f}');
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for level in [0, 2, 5] {
+            assert!(roundtrip("Hello, world!", level));
+            assert!(roundtrip("This! Is a `quote (100%)`.", level));
+            assert!(roundtrip("encoded 0123456789 as digits", level));
+            assert!(roundtrip("mIxEd CaSe\r\n\ttabs & [brackets] {}", level));
+            assert!(roundtrip("12345678987654321 num", level));
+            assert!(roundtrip("123456789876543211234567898765432112345678987654321", level));
+            assert!(roundtrip("abc1D234\x1B", level));
+            for text in ["A\x1bBC", "ABC\x1bD", "ab\x00\x7fcd", "x\x1b1234567890123456 y"] {
+                assert!(roundtrip(text, level), "{text:?}");
+                // over the stale codewords of a restored encoder
+                let mut expected = [0u8; 32];
+                expected[..4].copy_from_slice(b"Test");
+                expected[4..4 + text.len()].copy_from_slice(text.as_bytes());
+                assert!(roundtrip_with(|e| {
+                    let e = e.append_ascii("Test");
+                    let state = e.snapshot();
+                    let mut e = e.append_ascii("dirty buffer content ~~~~ 0123");
+                    e.restore(state);
+                    e.append_ascii(text)
+                }, &expected[..4 + text.len()], level), "{text:?}");
+            }
+            assert!(roundtrip("Ça marche très bien ✓", level));
+
+            assert!(roundtrip_with(|e| e.append_num(12345678987654321), b"12345678987654321", level));
+            assert!(roundtrip_with(|e| e.append_numeric_bytes(b"0042"), b"0042", level));
+            for len in [1, 5, 6, 7, 11, 12, 13] {
+                let bytes = &b"\x00\xFFbinary\x80\x7F data"[..len];
+                assert!(roundtrip_with(|e| e.append_bytes(bytes), bytes, level));
+            }
+            assert!(roundtrip_with(|e| e.append_ascii("Test").append_num(42).append_bytes(b"encode bin"),
+                b"Test42encode bin", level));
+        }
+        assert!(!roundtrip_with(|e| e.append_ascii("abc"), b"abd", 0));
+    }
+
     #[test]
     fn test_seal_normal() {
         let mut codewords = [0u16; 1 + 3 + 3 + 10 + 2];
@@ -602,6 +3368,98 @@ mod tests {
     }
 
     #[test]
+    fn test_try_seal_ecc_overflow() {
+        use super::SealError;
+        let mut codewords = [0u16; 3];
+        let res = PDF417Encoder::new(&mut codewords, false).try_seal(8);
+        assert_eq!(res, Err(SealError::EccOverflow { ecc: 512, capacity: 3 }));
+
+        let mut codewords = [0u16; 3];
+        let res = PDF417Encoder::new(&mut codewords, false).try_seal(9);
+        assert_eq!(res, Err(SealError::InvalidLevel(9)));
+
+        let mut codewords = [0u16; 3];
+        assert!(PDF417Encoder::new(&mut codewords, false).try_seal(0).is_ok());
+    }
+
+    #[test]
+    fn test_try_seal_data_overflow() {
+        use super::SealError;
+        let mut codewords = [0u16; 8];
+        let res = PDF417Encoder::new(&mut codewords, false).append_ascii("Overflow").try_seal(1);
+        assert_eq!(res, Err(SealError::DataOverflow { data: 6, capacity: 4, level: 1 }));
+
+        let mut codewords = [0u16; 11];
+        let res = PDF417Encoder::new(&mut codewords, true).append_ascii("Overflow").try_seal(0);
+        assert_eq!(res, Err(SealError::MicroDataOverflow { data: 6, capacity: 4, variant: 0 }));
+    }
+
+    #[test]
+    #[should_panic(expected = "data (6) exceeds capacity (4) for ECC level 1")]
+    fn test_seal_data_overflow() {
+        let mut codewords = [0u16; 8];
+        PDF417Encoder::new(&mut codewords, false).append_ascii("Overflow").seal(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "512 ECC codewords do not fit in a storage of 3 codewords")]
+    fn test_seal_ecc_overflow() {
+        let mut codewords = [0u16; 3];
+        PDF417Encoder::new(&mut codewords, false).seal(8);
+    }
+
+    #[test]
+    fn test_fit_ecc_capped() {
+        let mut codewords = [0u16; 100];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_ascii("Capped");
+        assert_eq!(ec.fit_ecc(), Some(5));
+        assert_eq!(ec.fit_ecc_capped(3), Some(3));
+        assert_eq!(ec.fit_ecc_capped(8), Some(5));
+
+        // 13 slots left: up to the level 2 (8 ECC codewords)
+        let mut codewords = [0u16; 20];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_bytes(b"tight");
+        assert_eq!(ec.fit_ecc_capped(5), Some(2));
+
+        let mut codewords = [0u16; 4];
+        let ec = PDF417Encoder::new(&mut codewords, false).append_ascii("full");
+        assert_eq!(ec.fit_ecc_capped(5), None);
+    }
+
+    #[test]
+    fn test_fit_seal_micro() {
+        let mut codewords = [0u16; 44 * 4];
+        let (variant, sealed) = PDF417Encoder::new(&mut codewords, true)
+            .append_ascii("Test")
+            .fit_seal_micro().unwrap();
+        assert_eq!((variant.rows(), variant.cols()), (11, 1));
+        assert_eq!(sealed.len(), 11);
+        assert_eq!(&sealed[..4], &[900, 19 * 30 + 27, 4 * 30 + 18, 19 * 30 + 29]);
+
+        let mut codewords = [0u16; 44 * 4];
+        let (variant, sealed) = PDF417Encoder::new(&mut codewords, true)
+            .append_bytes(&[0xAA; 40])
+            .fit_seal_micro().unwrap();
+        assert_eq!((variant.rows(), variant.cols()), (26, 2));
+        assert_eq!(sealed.len(), 52);
+
+        // too much data for any variant
+        let mut codewords = [0u16; 44 * 4];
+        let res = PDF417Encoder::new(&mut codewords, true)
+            .append_raw(&[900; 127])
+            .fit_seal_micro();
+        assert!(res.is_none());
+
+        // storage too small for the selected variant
+        let mut codewords = [0u16; 5];
+        let res = PDF417Encoder::new(&mut codewords, true)
+            .append_ascii("Test")
+            .fit_seal_micro();
+        assert!(res.is_none());
+    }
+
+    #[test]
+    #[allow(clippy::identity_op)]
     fn test_seal_micro() {
         use crate::tables::{M_PDF417_VARIANTS, M_PDF417_VARIANTS_COUNT};
         let mut codewords = [0u16; 1 + 3 + 3 + 10 + M_PDF417_VARIANTS[2 * M_PDF417_VARIANTS_COUNT + 0] as usize];
@@ -21,7 +21,7 @@
 //! 
 //! // Rendering
 //! let mut storage = [false; WIDTH * HEIGHT];
-//! PDF417::new(&input, ROWS, COLS, level).render(&mut storage[..]);
+//! PDF417::new(&input, ROWS, COLS, level).render().fill_bits(&mut storage[..]);
 //! ```
 //!
 //! ### Data Segments
@@ -30,7 +30,7 @@
 //! available types are:
 //! - **numeric**: efficient encoding of 44+ digit numbers
 //! - **ascii**: efficient encoding of text (alphanumeric + punctuation) with
-//!     support for non-displyable ASCII values which are encoded as raw bytes.
+//!   support for non-displyable ASCII values which are encoded as raw bytes.
 //! - **bytes**: binary data as bytes
 //! 
 //! An additional **UTF-8** mode is available which allows encoding of UTF-8 strings
@@ -52,21 +52,25 @@
 //! const HEIGHT: usize = m_pdf417_height!(ROWS);
 //!
 //! // High-level encoding
-//! let variant = get_variant(ROWS, COLS).unwrap();
+//! let variant = Variant::with_dimensions(ROWS, COLS).unwrap();
 //! let mut input = [0u16; (ROWS * COLS) as usize];
 //! PDF417Encoder::new(&mut input, true)
-//!     .append_num(12345678).seal(variant);
+//!     .append_num(12345678).seal(variant.into());
 //!
 //! // Rendering
 //! let mut storage = [false; WIDTH * HEIGHT];
-//! MicroPDF417::new(&input, variant).render(&mut storage[..]);
+//! MicroPDF417::from_variant(&input, variant).render().fill_bits(&mut storage[..]);
 //! ```
 //!
 //! Do not forget to set the `micro` parameter to true in [PDF417Encoder::new].
 
 #![no_std]
+#![forbid(unsafe_code)]
 //#![warn(missing_docs)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 mod tables;
 pub mod generators;
 pub mod builder;
@@ -74,31 +78,40 @@ pub mod ecc;
 pub mod high_level;
 
 use tables::*;
-use generators::{bitfield::Bitfield, PDF417Row, TruncatedPDF417Row, MicroPDF417Row};
+use generators::{bitfield::Bitfield, PDF417Row, TruncatedPDF417Row, CompactPDF417Row, MicroPDF417Row};
 
 pub use high_level::*;
-pub use tables::Variant;
+pub use tables::{Variant, RegularVariant, low_level};
 
 pub const START_PATTERN: Bitfield = Bitfield::new(0b11111111010101000, 17);
 pub const   END_PATTERN: Bitfield = Bitfield::new(0b111111101000101001, 18);
 
+/// Maximum number of codewords of a PDF417 symbol (rows \* cols).
+pub const MAX_SYMBOL_CODEWORDS: usize = 928;
+/// Modulus of the Galois field used by the error correction, codeword values
+/// are between 0 and `GF_MODULUS - 1`.
+pub const GF_MODULUS: u16 = 929;
+/// Base of the numeric and byte compactions, data codewords are below `BASE`
+/// and the greater values are control codewords (latches, shifts, ECI...).
+pub const BASE: u16 = 900;
+
 #[macro_export]
 /// Calculate the width in pixels of a PDF417 barcode according to the
 /// configuration (Columns, X scale, Is Truncated). Only the number of columns
 /// is required, other parameters can be omitted in order.
 macro_rules! pdf417_width {
     ($cols:expr) => {
-        pdf417_width!($cols, 1);
+        $crate::pdf417_width!($cols, 1)
     };
     ($cols:expr, $scale_x:expr) => {
-        pdf417_width!($cols, $scale_x, false);
+        $crate::pdf417_width!($cols, $scale_x, false)
     };
     ($cols:expr, $scale_x:expr, $truncated:expr) => {
         if $truncated {
-            (pdf417::START_PATTERN.size() as usize + 17 + $cols as usize * 17 + 1)
+            ($crate::START_PATTERN.size() as usize + 17 + $cols as usize * 17 + 1)
                 * $scale_x as usize
         } else {
-            (pdf417::START_PATTERN.size() as usize + 17 + $cols as usize * 17 + 17 + pdf417::END_PATTERN.size() as usize)
+            ($crate::START_PATTERN.size() as usize + 17 + $cols as usize * 17 + 17 + $crate::END_PATTERN.size() as usize)
                 * $scale_x as usize
         }
     };
@@ -110,7 +123,7 @@ macro_rules! pdf417_width {
 /// parameters can be omitted in order.
 macro_rules! pdf417_height {
     ($rows:expr) => {
-        pdf417_height!($rows, 1);
+        $crate::pdf417_height!($rows, 1)
     };
     ($rows:expr, $scale_y:expr) => {
         $rows as usize * $scale_y as usize
@@ -123,7 +136,7 @@ macro_rules! pdf417_height {
 /// other parameters can be omitted in order.
 macro_rules! m_pdf417_width {
     ($cols:expr) => {
-        m_pdf417_width!($cols, 1);
+        $crate::m_pdf417_width!($cols, 1)
     };
     ($cols:expr, $scale_x:expr) => {
         (10 + $cols as usize * 17 + ($cols as usize / 3) * 10 + 10 + 1)
@@ -137,13 +150,43 @@ macro_rules! m_pdf417_width {
 /// parameters can be omitted in order. Note that the default Y scale is 2.
 macro_rules! m_pdf417_height {
     ($rows:expr) => {
-        m_pdf417_height!($rows, 2);
+        $crate::m_pdf417_height!($rows, 2)
     };
     ($rows:expr, $scale_y:expr) => {
         $rows as usize * $scale_y as usize
     };
 }
 
+#[macro_export]
+/// Fails the compilation if a buffer of `buffer_len` codewords does not
+/// hold exactly the `rows * cols` codewords of a PDF417, the check done at
+/// runtime by [PDF417::new](builder::PDF417::new). It can be used at the
+/// module level or inside a function.
+///
+/// ```
+/// # use pdf417::*;
+/// const ROWS: u8 = 5;
+/// const COLS: u8 = 3;
+/// const INPUT: [u16; 15] = [0; 15];
+/// const_assert_pdf417!(INPUT.len(), ROWS, COLS);
+/// ```
+///
+/// A mismatch is reported by the compiler:
+///
+/// ```compile_fail
+/// # use pdf417::*;
+/// const_assert_pdf417!(16, 5, 3);
+/// ```
+macro_rules! const_assert_pdf417 {
+    ($buffer_len:expr, $rows:expr, $cols:expr) => {
+        const _: () = assert!($buffer_len as usize == $rows as usize * $cols as usize,
+            "The buffer length must be equal to rows * cols");
+    };
+}
+
 pub type PDF417<'a> = builder::PDF417<'a, PDF417Row<'a>>;
 pub type TruncatedPDF417<'a> = builder::PDF417<'a, TruncatedPDF417Row<'a>>;
+/// PDF417 keeping the right row indicators but ending with a single stop bar,
+/// see [CompactPDF417Row] for the reader compatibility caveats.
+pub type CompactPDF417<'a> = builder::PDF417<'a, CompactPDF417Row<'a>>;
 pub type MicroPDF417<'a> = builder::PDF417<'a, MicroPDF417Row<'a>>;
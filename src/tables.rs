@@ -90,7 +90,7 @@ pub const ECC_L8: [u16; 512] = [
 ];
 
 /// High level encoding to low level encoding
-pub const HL_TO_LL: [[u16; 929]; 3] = [
+pub const HL_TO_LL: [[u16; crate::GF_MODULUS as usize]; 3] = [
     // Table 1
     [
         0b1101010111000000,
@@ -2988,16 +2988,42 @@ pub const M_PDF417_CENTER: [u16; 52] = [
     0x2DC, 0x2DE
 ];
 
+/// Returns the low level pattern (bars and spaces) of the high level codeword
+/// `value` in the cluster `table` (0-2). The leading bar of every pattern is
+/// implicit (17 modules in total). This is the lookup used by the built-in
+/// PDF417 and MicroPDF417 renderers.
+#[inline]
+pub const fn low_level(table: u8, value: u16) -> u16 {
+    assert!(table < 3, "The cluster table must be between 0 and 2");
+    assert!(value < crate::GF_MODULUS, "The codeword value must be between 0 and 928");
+    HL_TO_LL[table as usize][value as usize]
+}
+
+/// Characters of the code page 437 from 0x80 to 0xFF (0x00 to 0x7F are ASCII).
+#[cfg(feature = "cp437")]
+pub const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{A0}',
+];
+
 #[derive(Debug, Clone, Copy)]
 pub struct Variant(u8);
 
 impl Variant {
     #[inline]
+    #[allow(clippy::identity_op)]
     pub const fn rows(&self) -> u8 {
         M_PDF417_VARIANTS[1 * M_PDF417_VARIANTS_COUNT + self.0 as usize] as u8
     }
 
     #[inline]
+    #[allow(clippy::erasing_op)]
     pub const fn cols(&self) -> u8 {
         M_PDF417_VARIANTS[0 * M_PDF417_VARIANTS_COUNT + self.0 as usize] as u8
     }
@@ -3007,9 +3033,43 @@ impl Variant {
         self.0
     }
 
+    /// Number of ECC codewords of this variant.
+    #[inline]
+    pub const fn ecc_count(&self) -> usize {
+        M_PDF417_VARIANTS[2 * M_PDF417_VARIANTS_COUNT + self.0 as usize] as usize
+    }
+
+    /// Number of data codewords (rows \* cols - ECC codewords) of this variant.
+    #[inline]
+    pub const fn data_capacity(&self) -> usize {
+        self.rows() as usize * self.cols() as usize - self.ecc_count()
+    }
+
+    /// Width of the symbol in modules (see [m_pdf417_width](crate::m_pdf417_width)).
+    #[inline]
+    pub const fn width(&self) -> u32 {
+        crate::m_pdf417_width!(self.cols()) as u32
+    }
+
+    /// Number of rows of the symbol, i.e. its height when rendered with a
+    /// vertical scale of 1 (see [Variant::module_height] for the height in
+    /// modules).
+    #[inline]
+    pub const fn height(&self) -> u32 {
+        self.rows() as u32
+    }
+
+    /// Height of the symbol in modules, MicroPDF417 rows being 2 modules
+    /// high (see [m_pdf417_height](crate::m_pdf417_height)).
+    #[inline]
+    pub const fn module_height(&self) -> u32 {
+        crate::m_pdf417_height!(self.rows()) as u32
+    }
+
     /// Get the variant number for a dimension (rows, cols). Returns None the
     /// combinaison of rows and cols is invalid (not supported) according to the
     /// MicroPDF417 specification.
+    #[allow(clippy::identity_op)]
     pub const fn with_dimensions(rows: u8, cols: u8) -> Option<Variant> {
         let mut start = 0;
         while start < M_PDF417_VARIANTS_COUNT && M_PDF417_VARIANTS[start] != cols as u16 {
@@ -3038,6 +3098,7 @@ impl Variant {
 
     /// Find a suitable variant that has at least `capacity` free codeword slots.
     /// None if capacity is too large to be stored by a MicroPDF417.
+    #[allow(clippy::identity_op, clippy::erasing_op)]
     pub const fn with_capacity(capacity: usize) -> Option<Variant> {
         let mut i = 0;
 
@@ -3056,6 +3117,114 @@ impl Variant {
             None
         }
     }
+
+    /// Find the variant with the largest data capacity whose dimensions do
+    /// not exceed `max_rows` and `max_cols`. None if no variant fits.
+    pub const fn largest_within(max_rows: u8, max_cols: u8) -> Option<Variant> {
+        let mut best: Option<Variant> = None;
+        let mut i = 0;
+
+        while i < M_PDF417_VARIANTS_COUNT {
+            let v = Variant(i as u8);
+            if v.rows() <= max_rows && v.cols() <= max_cols {
+                best = match best {
+                    Some(b) if b.data_capacity() >= v.data_capacity() => Some(b),
+                    _ => Some(v)
+                };
+            }
+            i += 1;
+        }
+
+        best
+    }
+}
+
+/// Descriptor of a regular PDF417 (rows, cols, ECC level) with the same
+/// accessors as the MicroPDF417 [Variant].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegularVariant {
+    rows: u8,
+    cols: u8,
+    level: u8,
+}
+
+impl RegularVariant {
+    /// Creates the descriptor of a regular PDF417 of `rows` rows (3-90),
+    /// `cols` columns (1-30) and of ECC level `level` (0-8). Returns None if
+    /// the configuration is invalid or if the ECC codewords and the length
+    /// codeword do not fit in the symbol.
+    pub const fn new(rows: u8, cols: u8, level: u8) -> Option<RegularVariant> {
+        match Self::try_new(rows, cols, level) {
+            Ok(v) => Some(v),
+            Err(_) => None,
+        }
+    }
+
+    /// Same as [RegularVariant::new] but returns a [ConfigError](crate::builder::ConfigError) describing
+    /// the invalid parameter. The validated descriptor can then be used to
+    /// create many symbols with [PDF417::from_config](crate::PDF417::from_config).
+    pub const fn try_new(rows: u8, cols: u8, level: u8) -> Result<RegularVariant, crate::builder::ConfigError> {
+        use crate::builder::{ConfigError, MIN_ROWS, MAX_ROWS, MIN_COLS, MAX_COLS};
+        if rows < MIN_ROWS || rows > MAX_ROWS {
+            return Err(ConfigError::InvalidRows(rows));
+        }
+        if cols < MIN_COLS || cols > MAX_COLS {
+            return Err(ConfigError::InvalidCols(cols));
+        }
+        if level > 8 {
+            return Err(ConfigError::InvalidLevel(level));
+        }
+        let total = rows as usize * cols as usize;
+        if total > crate::MAX_SYMBOL_CODEWORDS {
+            return Err(ConfigError::TooLarge { rows, cols });
+        }
+        let ecc = crate::ecc::ecc_count(level);
+        if ecc >= total {
+            return Err(ConfigError::EccOverflow { ecc, capacity: total });
+        }
+        Ok(RegularVariant { rows, cols, level })
+    }
+
+    #[inline]
+    pub const fn rows(&self) -> u8 {
+        self.rows
+    }
+
+    #[inline]
+    pub const fn cols(&self) -> u8 {
+        self.cols
+    }
+
+    #[inline]
+    pub const fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// Number of ECC codewords of the ECC level.
+    #[inline]
+    pub const fn ecc_count(&self) -> usize {
+        crate::ecc::ecc_count(self.level)
+    }
+
+    /// Number of data codewords (rows \* cols - ECC codewords), including
+    /// the length codeword.
+    #[inline]
+    pub const fn data_capacity(&self) -> usize {
+        self.rows as usize * self.cols as usize - self.ecc_count()
+    }
+
+    /// Width of the symbol in modules (see [pdf417_width](crate::pdf417_width)).
+    #[inline]
+    pub const fn width(&self) -> u32 {
+        crate::pdf417_width!(self.cols) as u32
+    }
+
+    /// Number of rows of the symbol, i.e. its height when rendered with a
+    /// vertical scale of 1.
+    #[inline]
+    pub const fn height(&self) -> u32 {
+        self.rows as u32
+    }
 }
 
 impl From<u8> for Variant {
@@ -3065,8 +3234,77 @@ impl From<u8> for Variant {
     }
 }
 
-impl Into<u8> for Variant {
-    fn into(self) -> u8 {
-        self.0
+impl From<Variant> for u8 {
+    fn from(variant: Variant) -> u8 {
+        variant.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{low_level, Variant, RegularVariant};
+    use crate::builder::ConfigError;
+    use crate::ecc::ecc_count;
+
+    #[test]
+    fn test_regular_variant() {
+        for (rows, cols, level) in [(3, 1, 0), (10, 5, 3), (30, 30, 8), (90, 10, 5)] {
+            let v = RegularVariant::new(rows, cols, level).unwrap();
+            assert_eq!((v.rows(), v.cols(), v.level()), (rows, cols, level));
+            assert_eq!(v.ecc_count(), ecc_count(level));
+            assert_eq!(v.data_capacity(), rows as usize * cols as usize - ecc_count(level));
+            assert_eq!(v.width() as usize, crate::pdf417_width!(cols));
+            assert_eq!(v.height(), rows as u32);
+        }
+        assert!(RegularVariant::new(2, 1, 0).is_none());
+        assert!(RegularVariant::new(3, 31, 0).is_none());
+        assert!(RegularVariant::new(90, 30, 0).is_none());
+        assert!(RegularVariant::new(10, 5, 9).is_none());
+        assert!(RegularVariant::new(10, 5, 5).is_none());
+
+        assert_eq!(RegularVariant::try_new(3, 31, 0), Err(ConfigError::InvalidCols(31)));
+        assert_eq!(RegularVariant::try_new(90, 30, 0), Err(ConfigError::TooLarge { rows: 90, cols: 30 }));
+        assert_eq!(RegularVariant::try_new(10, 5, 5), Err(ConfigError::EccOverflow { ecc: 64, capacity: 50 }));
+
+        let v = Variant::with_dimensions(11, 1).unwrap();
+        assert_eq!(v.width() as usize, crate::m_pdf417_width!(1));
+        assert_eq!(v.height(), 11);
+    }
+
+    #[test]
+    fn test_variant_module_dimensions() {
+        for (rows, cols) in [(11, 1), (8, 2), (6, 3), (4, 4), (44, 4)] {
+            let v = Variant::with_dimensions(rows, cols).unwrap();
+            assert_eq!(v.width() as usize, crate::m_pdf417_width!(cols));
+            assert_eq!(v.module_height() as usize, crate::m_pdf417_height!(rows));
+        }
+    }
+
+    #[test]
+    fn test_variant_largest_within() {
+        assert_eq!(Variant::largest_within(11, 1).map(|v| v.variant()), Some(0));
+        let v = Variant::largest_within(20, 2).unwrap();
+        assert_eq!((v.rows(), v.cols(), v.data_capacity()), (20, 2, 29));
+        let v = Variant::largest_within(4, 4).unwrap();
+        assert_eq!((v.rows(), v.cols()), (4, 4));
+        let v = Variant::largest_within(255, 255).unwrap();
+        assert_eq!((v.rows(), v.cols(), v.data_capacity()), (44, 4, 126));
+        assert!(Variant::largest_within(3, 4).is_none());
+        assert!(Variant::largest_within(10, 1).is_none());
+    }
+
+    #[test]
+    fn test_low_level() {
+        assert_eq!(low_level(0, 0), 0b1101010111000000);
+        assert_eq!(low_level(1, 0), 0b1111010101100000);
+        assert_eq!(low_level(1, 5), 0b1110101000100000);
+        assert_eq!(low_level(2, 0), 0b1010101111100000);
+        assert_eq!(low_level(2, 928), 0b1100011111101010);
+    }
+
+    #[test]
+    #[should_panic(expected = "The codeword value must be between 0 and 928")]
+    fn test_low_level_out_of_range() {
+        low_level(0, 929);
     }
 }